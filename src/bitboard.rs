@@ -0,0 +1,301 @@
+use crate::defines::*;
+use crate::game_state::GameState;
+use crate::zobrist;
+use std::fmt;
+
+// Maximum number of squares that fit into a single u128 bitmask
+pub const MAX_BITBOARD_SIZE: usize = 128;
+
+// Bitboard backed alternative to Board for the search. It implements the same
+// GameState (moves, results, hashing and winning squares) but none of Board's
+// notation, symmetry or input parsing helpers, so for now it is only used by
+// bench. Each player's marks are kept in a single bitmask and every winning
+// line is precomputed as a mask, so win, draw and move generation are a
+// handful of bit operations. Supports boards of up to MAX_BITBOARD_SIZE
+// squares (i.e. up to 11x11).
+#[derive(Debug, Clone)]
+pub struct BitBoard {
+    width: usize,
+    height: usize,
+    // number of marks in a row needed to win
+    k: usize,
+    x_bits: u128,
+    o_bits: u128,
+    // all squares of the board set to 1
+    full_mask: u128,
+    pub player_just_moved: Mark,
+    history: Vec<usize>,
+    win_masks: Vec<u128>,
+    // Zobrist hash of the position, kept up to date by make_move/take_move
+    hash: u64,
+}
+
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut board_string = String::new();
+
+        for row in 0..self.height {
+            let mut line = String::new();
+            for idx in row * self.width..(row + 1) * self.width {
+                let mark = match self.get_mark(idx) {
+                    Mark::NoPlayer => String::from("-"),
+                    Mark::X => String::from("X"),
+                    Mark::O => String::from("O"),
+                };
+
+                let var = format!("| {} ", mark);
+                line.push_str(&var);
+            }
+            board_string.push_str(&format!("\t{}|\n", line));
+        }
+
+        let player_to_move = self.update_player_jm(self.player_just_moved);
+
+        write!(f, "\n\tPlayer to move {:?}\n\n{}", player_to_move, board_string)
+    }
+}
+
+impl Default for BitBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitBoard {
+    pub fn new() -> BitBoard {
+        // Returns a new board initialized to "0"/default values
+        BitBoard::with_dimensions(ROWS, ROWS, ROWS)
+    }
+
+    // Returns an empty width x height board where k marks in a row
+    // (horizontally, vertically or diagonally) win the game
+    pub fn with_dimensions(width: usize, height: usize, k: usize) -> BitBoard {
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(width * height <= MAX_BITBOARD_SIZE, "Board is too big for a bitboard");
+        assert!(k > 0 && k <= width.max(height), "Win length must fit on the board");
+
+        let win_masks = get_result_lines(width, height, k).
+            iter().
+            flatten().
+            map(|line| line.iter().fold(0, |mask, idx| mask | (1u128 << idx))).
+            collect::<Vec<u128>>();
+
+        let size = width * height;
+        let full_mask = if size == MAX_BITBOARD_SIZE { !0 } else { (1u128 << size) - 1 };
+
+        BitBoard {
+            width,
+            height,
+            k,
+            x_bits: 0,
+            o_bits: 0,
+            full_mask,
+            player_just_moved: Mark::O,
+            history: Vec::new(),
+            win_masks,
+            hash: zobrist::dimensions_key(width, height, k),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.k
+    }
+
+    // 64-bit Zobrist hash of the position (marks and player to move)
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn get_mark(&self, idx: usize) -> Mark {
+        let bit = 1u128 << idx;
+        if self.x_bits & bit != 0 {
+            Mark::X
+        } else if self.o_bits & bit != 0 {
+            Mark::O
+        } else {
+            Mark::NoPlayer
+        }
+    }
+
+    pub fn update_player_jm(&self, player_jm: Mark) -> Mark {
+        match player_jm {
+            Mark::X => Mark::O,
+            Mark::O => Mark::X,
+            _ => Mark::NoPlayer,
+        }
+    }
+
+    // Used for parsing user input move
+    pub fn make_move_safe(&mut self, move_int: usize) -> Result<(), String> {
+        if !self.get_moves().contains(&move_int) {
+            return Err(format!("Invalid move value: {}", move_int));
+        }
+
+        self.make_move(move_int);
+
+        Ok(())  // return empty result if everything went okay
+    }
+
+    fn player_bits(&self, player: Mark) -> u128 {
+        match player {
+            Mark::X => self.x_bits,
+            Mark::O => self.o_bits,
+            Mark::NoPlayer => 0,
+        }
+    }
+
+    // Empty squares that would complete a line for `player`, i.e. squares
+    // where `player` wins immediately (or has to be blocked)
+    pub fn winning_squares(&self, player: Mark) -> Vec<usize> {
+        let bits = self.player_bits(player);
+        let empty = !(self.x_bits | self.o_bits) & self.full_mask;
+        let mut squares = 0u128;
+        for mask in self.win_masks.iter() {
+            let missing = mask & !bits;
+            if missing.count_ones() == 1 && missing & empty != 0 {
+                squares |= missing;
+            }
+        }
+
+        let mut winning_squares = Vec::with_capacity(squares.count_ones() as usize);
+        while squares != 0 {
+            winning_squares.push(squares.trailing_zeros() as usize);
+            squares &= squares - 1; // clear lowest set bit
+        }
+
+        winning_squares
+    }
+
+    fn has_won(&self, player: Mark) -> bool {
+        let bits = self.player_bits(player);
+        self.win_masks.iter().any(|mask| bits & mask == *mask)
+    }
+}
+
+impl GameState for BitBoard {
+    type Move = usize;
+    type Player = Mark;
+
+    fn player_just_moved(&self) -> Mark {
+        self.player_just_moved
+    }
+
+    fn position_hash(&self) -> u64 {
+        self.hash
+    }
+
+    // only used for valid/verified input, any other input goes through make_move_safe
+    fn make_move(&mut self, move_int: usize) {
+        self.player_just_moved = self.update_player_jm(self.player_just_moved);
+        match self.player_just_moved {
+            Mark::X => self.x_bits |= 1 << move_int,
+            Mark::O => self.o_bits |= 1 << move_int,
+            Mark::NoPlayer => (),
+        }
+        self.history.push(move_int);
+        self.hash ^= zobrist::square_key(move_int, self.player_just_moved) ^ zobrist::side_key();
+    }
+
+    fn take_move(&mut self) {
+        let move_int = self.history.pop().expect("No move to take back!");
+        self.hash ^= zobrist::square_key(move_int, self.get_mark(move_int)) ^ zobrist::side_key();
+        let bit = !(1u128 << move_int);
+        self.x_bits &= bit;
+        self.o_bits &= bit;
+        self.player_just_moved = self.update_player_jm(self.player_just_moved);
+    }
+
+    fn get_moves(&self) -> Vec<usize> {
+        if self.get_result(self.player_just_moved).is_some() {
+            return Vec::new();
+        }
+
+        let mut empty = !(self.x_bits | self.o_bits) & self.full_mask;
+        let mut moves = Vec::with_capacity(empty.count_ones() as usize);
+        while empty != 0 {
+            moves.push(empty.trailing_zeros() as usize);
+            empty &= empty - 1; // clear lowest set bit
+        }
+
+        moves
+    }
+
+    fn get_result(&self, player_jm: Mark) -> Option<f32> {
+        // Only the player who just moved can have completed a line
+        let last_player = self.player_just_moved;
+        if last_player != Mark::NoPlayer && self.has_won(last_player) {
+            return if last_player == player_jm { Some(WIN) } else { Some(LOSS) };
+        }
+
+        // If no result and no moves left => DRAW
+        if self.x_bits | self.o_bits == self.full_mask {
+            return Some(DRAW);
+        }
+
+        None
+    }
+
+    fn winning_moves(&mut self) -> Vec<usize> {
+        self.winning_squares(self.update_player_jm(self.player_just_moved))
+    }
+
+    fn safe_moves(&mut self) -> Vec<usize> {
+        // Marks never move, so a threat can only be stopped by taking its square
+        let threats = self.winning_squares(self.player_just_moved);
+        match threats.len() {
+            0 => self.get_moves(),
+            1 => threats,
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::uct::rng_from_seed;
+    use rand::seq::SliceRandom;
+
+    fn assert_same_position(board: &Board, bitboard: &BitBoard) {
+        assert_eq!(bitboard.get_moves(), board.get_moves());
+        assert_eq!(bitboard.get_result(Mark::X), board.get_result(Mark::X));
+        assert_eq!(bitboard.get_result(Mark::O), board.get_result(Mark::O));
+        assert_eq!(bitboard.hash(), board.hash());
+        assert_eq!(bitboard.winning_squares(Mark::X), board.winning_squares(Mark::X));
+        assert_eq!(bitboard.winning_squares(Mark::O), board.winning_squares(Mark::O));
+    }
+
+    #[test]
+    fn bitboard_agrees_with_board() {
+        let mut rng = rng_from_seed(Some(1));
+        for &(width, height, k) in [(3, 3, 3), (4, 4, 3), (5, 4, 4), (7, 6, 4), (11, 11, 5), (1, 5, 3)].iter() {
+            for _game in 0..20 {
+                let mut board = Board::with_dimensions(width, height, k);
+                let mut bitboard = BitBoard::with_dimensions(width, height, k);
+                assert_same_position(&board, &bitboard);
+
+                while let Some(&move_) = board.get_moves().choose(&mut rng) {
+                    board.make_move(move_);
+                    bitboard.make_move(move_);
+                    assert_same_position(&board, &bitboard);
+                }
+
+                // and back again to the empty board
+                while !board.history().is_empty() {
+                    board.take_move();
+                    bitboard.take_move();
+                    assert_same_position(&board, &bitboard);
+                }
+            }
+        }
+    }
+}
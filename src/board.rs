@@ -1,26 +1,96 @@
-
 use crate::defines::*;
+use crate::game_state::GameState;
+use crate::zobrist;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Winning lines of a width x height board with win length k. They never
+// change, so every board of the same dimensions shares one copy.
+#[derive(Debug)]
+struct LineTables {
+    result_lines: Vec<Vec<usize>>,
+    // indices into result_lines of every line passing through each square
+    square_lines: Vec<Vec<usize>>,
+}
+
+type LineTablesCache = Mutex<HashMap<(usize, usize, usize), Arc<LineTables>>>;
+
+static LINE_TABLES: OnceLock<LineTablesCache> = OnceLock::new();
+
+impl LineTables {
+    // Tables for the given dimensions, built on first use
+    fn get(width: usize, height: usize, k: usize) -> Arc<LineTables> {
+        let cache = LINE_TABLES.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut cache = cache.lock().expect("Line table cache poisoned!");
+        cache.entry((width, height, k)).or_insert_with(|| Arc::new(LineTables::new(width, height, k))).clone()
+    }
+
+    fn new(width: usize, height: usize, k: usize) -> LineTables {
+        let result_lines = get_result_lines(width, height, k).
+            into_iter().
+            flatten().
+            collect::<Vec<Vec<usize>>>();
+
+        let mut square_lines = vec![Vec::new(); width * height];
+        for (line_idx, line) in result_lines.iter().enumerate() {
+            for square in line.iter() {
+                square_lines[*square].push(line_idx);
+            }
+        }
+
+        LineTables { result_lines, square_lines }
+    }
+}
 
 // TODO: What's the difference between clone and copy ???
 #[derive(Debug, Clone)]
 pub struct Board {
-    pos: [Mark; BOARD_SIZE],
+    width: usize,
+    height: usize,
+    // number of marks in a row needed to win
+    k: usize,
+    pos: Vec<Mark>,
     pub player_just_moved: Mark,
     // fixed size array (instead of Vec) and a counter to keep
     // track of the move number will be more performant
     history: Vec<usize>,
-    result_lines: Vec<Vec<Vec<usize>>>,
+    // winning lines of the board, shared by all boards of the same size
+    lines: Arc<LineTables>,
+    // cached game outcome: the winner and the ply (history length) on which
+    // the winning line was completed
+    winner: Option<(Mark, usize)>,
+    // number of occupied squares
+    filled: usize,
+    // Zobrist hash of the position, kept up to date by make_move/take_move
+    hash: u64,
+}
+
+// Positions are compared by their Zobrist hash only
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Eq for Board {}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
 }
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut board_string = String::new();
 
-        for row_line in self.result_lines[1].iter() {
+        for row_line in self.pos.chunks(self.width) {
             let mut line = String::new();
-            for idx in row_line.iter() {
-                let mark = match self.pos[*idx] {
+            for square in row_line.iter() {
+                let mark = match square {
                     Mark::NoPlayer => String::from("-"),
                     Mark::X => String::from("X"),
                     Mark::O => String::from("O"),
@@ -42,15 +112,304 @@ impl fmt::Display for Board {
     }
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn mark_char(mark: Mark) -> char {
+    match mark {
+        Mark::X => 'X',
+        Mark::O => 'O',
+        Mark::NoPlayer => '-',
+    }
+}
+
+// Parses the board notation written by Board::to_notation. The player to move
+// and the win length can be left out, the player to move then follows from
+// the number of marks. As the notation doesn't record the order of the moves,
+// the board gets a made up history that leads to the position.
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(notation: &str) -> Result<Board, String> {
+        let fields = notation.split_whitespace().collect::<Vec<&str>>();
+        if fields.is_empty() || fields.len() > 3 {
+            return Err(format!("Expected \"<rows> [<player to move>] [<win length>]\", got \"{}\"", notation));
+        }
+
+        let mut pos = Vec::new();
+        let rows = fields[0].split('/').collect::<Vec<&str>>();
+        for row in rows.iter() {
+            for c in row.chars() {
+                pos.push(match c {
+                    'X' | 'x' => Mark::X,
+                    'O' | 'o' => Mark::O,
+                    '-' | '.' => Mark::NoPlayer,
+                    _ => return Err(format!("Invalid square '{}', expected X, O or -", c)),
+                });
+            }
+        }
+
+        let (width, height) = (rows[0].chars().count(), rows.len());
+        if width == 0 || rows.iter().any(|row| row.chars().count() != width) {
+            return Err(String::from("All rows must have the same, non-zero length"));
+        }
+
+        let k = match fields.get(2) {
+            Some(k) => k.parse::<usize>().map_err(|_| format!("Invalid win length: {}", k))?,
+            None => width.min(height),
+        };
+        if k == 0 || k > width.max(height) {
+            return Err(format!("Win length {} doesn't fit on a {}x{} board", k, width, height));
+        }
+
+        let squares_of = |mark: Mark| (0..pos.len()).filter(|idx| pos[*idx] == mark).collect::<Vec<usize>>();
+        let (mut x_squares, mut o_squares) = (squares_of(Mark::X), squares_of(Mark::O));
+        let (x_count, o_count) = (x_squares.len(), o_squares.len());
+
+        // the players alternate, so the first player has at most one mark more
+        let to_move = match fields.get(1).map(|s| s.to_lowercase()).as_deref() {
+            Some("x") => Mark::X,
+            Some("o") => Mark::O,
+            Some(other) => return Err(format!("Invalid player to move: {}", other)),
+            None if o_count > x_count => Mark::X,
+            None if x_count > o_count => Mark::O,
+            None => Mark::X,
+        };
+        let first = if x_count == o_count + 1 && to_move == Mark::O {
+            Mark::X
+        } else if o_count == x_count + 1 && to_move == Mark::X {
+            Mark::O
+        } else if x_count == o_count {
+            to_move
+        } else {
+            return Err(format!("Impossible mark counts: {} X and {} O with {:?} to move", x_count, o_count, to_move));
+        };
+
+        let lines = LineTables::get(width, height, k);
+        let won_lines = |mark: Mark| lines.result_lines.iter().filter(|line| line.iter().all(|idx| pos[*idx] == mark)).collect::<Vec<_>>();
+        let (x_lines, o_lines) = (won_lines(Mark::X), won_lines(Mark::O));
+
+        // The winner's last move has to be part of every line they completed,
+        // it is played last so the game doesn't end before the position is set up
+        let winner = match (x_lines.is_empty(), o_lines.is_empty()) {
+            (true, true) => None,
+            (false, false) => return Err(String::from("Both players have a winning line")),
+            (false, true) => Some((Mark::X, &x_lines, &mut x_squares)),
+            (true, false) => Some((Mark::O, &o_lines, &mut o_squares)),
+        };
+        let last_move = match winner {
+            Some((winner, winner_lines, winner_squares)) => {
+                if winner == to_move {
+                    return Err(format!("{:?} has already won, it can't be their move", winner));
+                }
+
+                let shared_square = winner_squares.
+                    iter().
+                    position(|square| winner_lines.iter().all(|line| line.contains(square))).
+                    ok_or_else(|| format!("{:?} has winning lines that can't have been completed by a single move", winner))?;
+                Some(winner_squares.remove(shared_square))
+            }
+            None => None,
+        };
+
+        let mut board = Board::with_dimensions(width, height, k).with_first_player(first);
+        let (mut x_squares, mut o_squares) = (x_squares.into_iter(), o_squares.into_iter());
+        loop {
+            let squares = if board.update_player_jm(board.player_just_moved) == Mark::X { &mut x_squares } else { &mut o_squares };
+            match squares.next() {
+                Some(square) => board.make_move(square),
+                None => break,
+            }
+        }
+        if let Some(square) = last_move {
+            board.make_move(square);
+        }
+
+        Ok(board)
+    }
+}
+
 impl Board {
     pub fn new() -> Board {
         // Returns a new board initialized to "0"/default values
+        Board::with_dimensions(ROWS, ROWS, ROWS)
+    }
+
+    // Returns an empty width x height board where k marks in a row
+    // (horizontally, vertically or diagonally) win the game
+    pub fn with_dimensions(width: usize, height: usize, k: usize) -> Board {
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(k > 0 && k <= width.max(height), "Win length must fit on the board");
+
         Board {
-            pos: [Mark::NoPlayer; BOARD_SIZE],
+            width,
+            height,
+            k,
+            pos: vec![Mark::NoPlayer; width * height],
             player_just_moved: Mark::O,
             history: Vec::new(),
-            result_lines: get_result_lines(),
+            lines: LineTables::get(width, height, k),
+            winner: None,
+            filled: 0,
+            hash: zobrist::dimensions_key(width, height, k),
+        }
+    }
+
+    // Lets `first` make the first move instead of X. Only for empty boards.
+    pub fn with_first_player(mut self, first: Mark) -> Board {
+        assert!(self.history.is_empty(), "The game has already started");
+        assert!(first != Mark::NoPlayer, "NoPlayer can't move");
+
+        if first != self.first_player() {
+            self.player_just_moved = self.update_player_jm(first);
+            // the side key is set whenever O is to move
+            self.hash ^= zobrist::side_key();
+        }
+
+        self
+    }
+
+    // The player who made (or is going to make) the first move of the game
+    pub fn first_player(&self) -> Mark {
+        if self.history.len() % 2 == 1 {
+            self.player_just_moved
+        } else {
+            self.update_player_jm(self.player_just_moved)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.k
+    }
+
+    pub fn get_mark(&self, idx: usize) -> Mark {
+        self.pos[idx]
+    }
+
+    // Number of moves played so far
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+
+    // Moves played so far, oldest first
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    // Empty squares that would complete a line for `player`, i.e. squares
+    // where `player` wins immediately (or has to be blocked)
+    pub fn winning_squares(&self, player: Mark) -> Vec<usize> {
+        let mut squares = Vec::new();
+        for line in self.lines.result_lines.iter() {
+            let marks = line.iter().filter(|x| self.pos[**x] == player).count();
+            let empty = line.iter().filter(|x| self.pos[**x] == Mark::NoPlayer).collect::<Vec<&usize>>();
+            if marks == self.k - 1 && empty.len() == 1 && !squares.contains(empty[0]) {
+                squares.push(*empty[0]);
+            }
+        }
+
+        squares.sort_unstable();
+        squares
+    }
+
+    // 64-bit Zobrist hash of the position (marks and player to move)
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Symmetries (see transform_square) that map this board onto itself:
+    // all 8 rotations/reflections for square boards, only the identity,
+    // the 180 degree rotation and the two mirrors for rectangular ones
+    pub fn symmetries(&self) -> Vec<usize> {
+        if self.width == self.height {
+            (0..8).collect()
+        } else {
+            vec![0, 2, 4, 5]
+        }
+    }
+
+    // Maps a square to its position under one of the dihedral symmetries:
+    // 0 - identity, 1/2/3 - rotation by 90/180/270 degrees clockwise,
+    // 4 - mirror left-right, 5 - mirror top-bottom,
+    // 6 - mirror along the main diagonal, 7 - mirror along the anti-diagonal
+    pub fn transform_square(&self, square: usize, symmetry: usize) -> usize {
+        let (row, col) = (square / self.width, square % self.width);
+        let (last_row, last_col) = (self.height - 1, self.width - 1);
+
+        let (new_row, new_col) = match symmetry {
+            0 => (row, col),
+            1 => (col, last_row - row),
+            2 => (last_row - row, last_col - col),
+            3 => (last_col - col, row),
+            4 => (row, last_col - col),
+            5 => (last_row - row, col),
+            6 => (col, row),
+            7 => (last_col - col, last_row - row),
+            _ => panic!("Invalid symmetry: {}", symmetry),
+        };
+
+        new_row * self.width + new_col
+    }
+
+    // The symmetry that undoes `symmetry`
+    pub fn inverse_symmetry(symmetry: usize) -> usize {
+        match symmetry {
+            1 => 3,
+            3 => 1,
+            _ => symmetry,
+        }
+    }
+
+    // Zobrist hash the board would have after applying `symmetry`
+    fn transformed_hash(&self, symmetry: usize) -> u64 {
+        let mut hash = zobrist::dimensions_key(self.width, self.height, self.k);
+        if self.player_just_moved == Mark::X {
+            hash ^= zobrist::side_key();
         }
+
+        for (square, mark) in self.pos.iter().enumerate() {
+            hash ^= zobrist::square_key(self.transform_square(square, symmetry), *mark);
+        }
+
+        hash
+    }
+
+    // Hash shared by all boards that are rotations/reflections of each other
+    pub fn canonical_hash(&self) -> u64 {
+        self.symmetries().iter().map(|s| self.transformed_hash(*s)).min().expect("No symmetries!")
+    }
+
+    // Returns the canonical orientation of this board (the one with the smallest
+    // hash) and the symmetry that produced it. Moves played on the canonical board
+    // map back to this board with transform_square(move, inverse_symmetry(symmetry))
+    pub fn canonical(&self) -> (Board, usize) {
+        let symmetry = *self.symmetries().
+            iter().
+            min_by_key(|s| self.transformed_hash(**s)).
+            expect("No symmetries!");
+
+        (self.transformed(symmetry), symmetry)
+    }
+
+    // Returns a copy of the board with `symmetry` applied to every move played
+    pub fn transformed(&self, symmetry: usize) -> Board {
+        let mut board = Board::with_dimensions(self.width, self.height, self.k).with_first_player(self.first_player());
+        for move_int in self.history.iter() {
+            board.make_move(self.transform_square(*move_int, symmetry));
+        }
+
+        board
     }
     pub fn update_player_jm(&self, player_jm: Mark) -> Mark {
         match player_jm {
@@ -60,13 +419,6 @@ impl Board {
         }
     }
 
-    // only used for valid/verified input, any other input goes through make_move_safe
-    pub fn make_move(&mut self, move_int: usize) {
-        self.player_just_moved = self.update_player_jm(self.player_just_moved);
-        self.pos[move_int] = self.player_just_moved;
-        self.history.push(move_int);
-    }
-
     // Used for parsing user input move
     pub fn make_move_safe(&mut self, move_int: usize) -> Result<(), String> {
         if !self.get_moves().contains(&move_int) {
@@ -78,9 +430,129 @@ impl Board {
         Ok(())  // return empty result if everything went okay
     }
 
-    pub fn take_move(&mut self) {
+    // Parses a square typed by the user: a square index ("4"), a column letter
+    // followed by a row number ("b2") or a row and a column number ("2,3" or
+    // "2 3"). Rows and columns are counted from 1, starting at the top left.
+    // Doesn't check whether the square is empty.
+    pub fn parse_square(&self, input: &str) -> Result<usize, String> {
+        let input = input.trim().to_lowercase();
+        let parse_number = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("Not a square: {}", input));
+
+        let (row, col) = if let Some(first) = input.chars().next().filter(|c| c.is_ascii_lowercase()) {
+            (parse_number(&input[1..])?, first as usize - 'a' as usize + 1)
+        } else {
+            let parts = input.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| !s.is_empty()).collect::<Vec<&str>>();
+            match parts.as_slice() {
+                [index] => {
+                    let index = parse_number(index)?;
+                    if index >= self.width * self.height {
+                        return Err(format!("Square {} is off the board", index));
+                    }
+                    return Ok(index);
+                }
+                [row, col] => (parse_number(row)?, parse_number(col)?),
+                _ => return Err(format!("Not a square: {}", input)),
+            }
+        };
+
+        if row == 0 || row > self.height || col == 0 || col > self.width {
+            return Err(format!("Square {} is off the board", input));
+        }
+
+        Ok((row - 1) * self.width + col - 1)
+    }
+
+    // Name of a square in the letter + row notation accepted by parse_square,
+    // e.g. "b2" for the center of a 3x3 board. Falls back to the square index
+    // on boards with more columns than letters.
+    pub fn square_name(&self, square: usize) -> String {
+        let (row, col) = (square / self.width, square % self.width);
+        if self.width <= 26 {
+            format!("{}{}", (b'a' + col as u8) as char, row + 1)
+        } else {
+            square.to_string()
+        }
+    }
+
+    // Position in board notation: the rows from top to bottom separated by '/',
+    // with X, O and - for an empty square, followed by the player to move and,
+    // if it isn't the board's shorter side, the win length. E.g. "X-O/-X-/--O x"
+    pub fn to_notation(&self) -> String {
+        let rows = self.pos.
+            chunks(self.width).
+            map(|row| row.iter().map(|mark| mark_char(*mark)).collect::<String>()).
+            collect::<Vec<String>>();
+        let to_move = mark_char(self.update_player_jm(self.player_just_moved)).to_ascii_lowercase();
+
+        if self.k == self.width.min(self.height) {
+            format!("{} {}", rows.join("/"), to_move)
+        } else {
+            format!("{} {} {}", rows.join("/"), to_move, self.k)
+        }
+    }
+
+    // Moves played so far in move list notation, e.g. "b2 a1 c3"
+    pub fn to_move_list(&self) -> String {
+        self.history.iter().map(|m| self.square_name(*m)).collect::<Vec<String>>().join(" ")
+    }
+
+    // Plays a move list: squares as indices or in letter + row notation (see
+    // parse_square), separated by spaces or commas
+    pub fn play_moves(&mut self, moves: &str) -> Result<(), String> {
+        for square in moves.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| !s.is_empty()) {
+            let move_int = self.parse_square(square)?;
+            self.make_move_safe(move_int).map_err(|_| format!("Illegal move: {}", square))?;
+        }
+
+        Ok(())
+    }
+
+    // Checks only the lines going through move_int, i.e. the only lines
+    // that could have been completed by the move just played there
+    fn completes_line(&self, move_int: usize) -> bool {
+        let mark = self.pos[move_int];
+        self.lines.square_lines[move_int].iter().any(|line_idx| {
+            self.lines.result_lines[*line_idx].iter().all(|x| self.pos[*x] == mark)
+        })
+    }
+}
+
+impl GameState for Board {
+    type Move = usize;
+    type Player = Mark;
+
+    fn player_just_moved(&self) -> Mark {
+        self.player_just_moved
+    }
+
+    fn position_hash(&self) -> u64 {
+        self.hash
+    }
+
+    // only used for valid/verified input, any other input goes through make_move_safe
+    fn make_move(&mut self, move_int: usize) {
+        self.player_just_moved = self.update_player_jm(self.player_just_moved);
+        self.pos[move_int] = self.player_just_moved;
+        self.history.push(move_int);
+        self.filled += 1;
+        self.hash ^= zobrist::square_key(move_int, self.player_just_moved) ^ zobrist::side_key();
+
+        if self.winner.is_none() && self.completes_line(move_int) {
+            self.winner = Some((self.player_just_moved, self.history.len()));
+        }
+    }
+
+    fn take_move(&mut self) {
         if let Some(move_int) = self.history.pop() {
+            if let Some((_, ply)) = self.winner {
+                if ply > self.history.len() {
+                    self.winner = None;
+                }
+            }
+
+            self.hash ^= zobrist::square_key(move_int, self.pos[move_int]) ^ zobrist::side_key();
             self.pos[move_int] = Mark::NoPlayer;
+            self.filled -= 1;
             self.player_just_moved = self.update_player_jm(self.player_just_moved);
         } else {
             println!("History is empty");
@@ -88,42 +560,157 @@ impl Board {
         }
     }
 
-    pub fn get_moves(&self) -> Vec<usize> {
-        if let Some(_) = self.get_result(self.player_just_moved) {
+    fn get_moves(&self) -> Vec<usize> {
+        if self.get_result(self.player_just_moved).is_some() {
             Vec::new() // return empty vector
         } else {
             // Return a vector of all indices of pos which are equal to NoPlayer
-            (0..BOARD_SIZE).filter(|x| self.pos[*x] == Mark::NoPlayer).collect::<Vec<usize>>()
+            (0..self.pos.len()).filter(|x| self.pos[*x] == Mark::NoPlayer).collect::<Vec<usize>>()
         }
     }
 
-    fn evaluate_lines(&self, lines: &Vec<Vec<usize>>, player_jm: &Mark) -> Option<f32> {
-        for line in lines.iter() {
-            let result = line.iter().map(|x| self.pos[*x] as i8).collect::<Vec<i8>>();
-            // the first element of result vec would also be the winner mark if line has result
-            let potential_winner = result[0];
-            // sum all elements in a row to find if there is a winner
-            let result: i8 = result.iter().sum();
-            if result.abs() == ROWS as i8 {
-                return if potential_winner == *player_jm as i8 { Some(WIN) } else { Some(LOSS) };
-            }
+    fn get_result(&self, player_jm: Mark) -> Option<f32> {
+        if let Some((winner, _)) = self.winner {
+            return if winner == player_jm { Some(WIN) } else { Some(LOSS) };
+        }
+
+        // If no result and no moves left => DRAW
+        if self.filled == self.pos.len() {
+            return Some(DRAW);
         }
 
-        return None;
+        None
+    }
+
+    fn get_unique_moves(&self) -> Vec<usize> {
+        // Symmetries that leave the position unchanged make some moves equivalent,
+        // keep only the smallest square of each group of equivalent moves
+        let stabilizers = self.symmetries().
+            into_iter().
+            filter(|s| *s != 0 && self.transformed_hash(*s) == self.hash).
+            collect::<Vec<usize>>();
+
+        self.get_moves().
+            into_iter().
+            filter(|m| stabilizers.iter().all(|s| self.transform_square(*m, *s) >= *m)).
+            collect::<Vec<usize>>()
+    }
+
+    fn winning_moves(&mut self) -> Vec<usize> {
+        self.winning_squares(self.update_player_jm(self.player_just_moved))
     }
 
-    pub fn get_result(&self, player_jm: Mark) -> Option<f32> {
-        for line in self.result_lines.iter() {
-            if let Some(winner) = self.evaluate_lines(&line, &player_jm) {
-                return Some(winner);
+    fn safe_moves(&mut self) -> Vec<usize> {
+        // Marks never move, so a threat can only be stopped by taking its square
+        let threats = self.winning_squares(self.player_just_moved);
+        match threats.len() {
+            0 => self.get_moves(),
+            1 => threats,
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uct::rng_from_seed;
+    use rand::seq::SliceRandom;
+
+    fn parse_error(notation: &str) -> String {
+        notation.parse::<Board>().expect_err("Impossible position was accepted")
+    }
+
+    #[test]
+    fn impossible_positions_are_rejected() {
+        assert!(parse_error("XX-/X--/--- o").starts_with("Impossible mark counts"));
+        assert!(parse_error("XXX/---/--- o").starts_with("Impossible mark counts"));
+        assert_eq!(parse_error("XXX/OOO/--- x"), "Both players have a winning line");
+        assert_eq!(parse_error("XXX/OO-/--O x"), "X has already won, it can't be their move");
+        // two X lines without a common square on a 4x4 board with k = 3
+        assert_eq!(parse_error("XXX-/OO-O/XXX-/O-O- o 3"), "X has winning lines that can't have been completed by a single move");
+    }
+
+    #[test]
+    fn finished_positions_are_accepted() {
+        let board = "XXX/OO-/--- o".parse::<Board>().unwrap();
+        assert_eq!(board.get_result(board.player_just_moved), Some(WIN));
+        assert_eq!(board.player_just_moved, Mark::X);
+
+        // two lines sharing the last move
+        let board = "XXX/OXO/OOX o".parse::<Board>().unwrap();
+        assert_eq!(board.get_result(Mark::X), Some(WIN));
+    }
+
+    #[test]
+    fn notation_round_trip_keeps_the_position() {
+        let mut rng = rng_from_seed(Some(1));
+        for &(width, height, k) in [(3, 3, 3), (4, 4, 3), (5, 3, 3), (7, 6, 4)].iter() {
+            for &first in [Mark::X, Mark::O].iter() {
+                for _game in 0..20 {
+                    let mut board = Board::with_dimensions(width, height, k).with_first_player(first);
+                    loop {
+                        let parsed = board.to_notation().parse::<Board>().unwrap();
+                        assert_eq!(parsed.to_notation(), board.to_notation());
+                        assert_eq!(parsed.hash(), board.hash());
+                        assert_eq!(parsed.get_moves(), board.get_moves());
+
+                        match board.get_moves().choose(&mut rng) {
+                            Some(&move_) => board.make_move(move_),
+                            None => break,
+                        }
+                    }
+                }
             }
         }
+    }
 
-        // If no result and no moves left => DRAW
-        if self.pos.iter().fold(0, |acc, x| acc + (*x as i8).abs()) == BOARD_SIZE as i8 {
-            return Some(DRAW);
+    #[test]
+    fn unique_moves_drop_symmetric_squares() {
+        let mut board = Board::new();
+        assert_eq!(board.get_unique_moves(), vec![0, 1, 4]);
+        board.make_move(4);
+        assert_eq!(board.get_unique_moves(), vec![0, 1]);
+        // a corner only leaves the diagonal through it as a symmetry
+        let board = "X--/---/--- o".parse::<Board>().unwrap();
+        assert_eq!(board.get_unique_moves(), vec![1, 2, 4, 5, 8]);
+    }
+
+    #[test]
+    fn rectangular_boards_keep_four_symmetries() {
+        let board = Board::with_dimensions(4, 3, 3);
+        assert_eq!(board.symmetries(), vec![0, 2, 4, 5]);
+        // every square is mapped back onto the board
+        for symmetry in board.symmetries() {
+            let mut squares = (0..12).map(|square| board.transform_square(square, symmetry)).collect::<Vec<usize>>();
+            squares.sort();
+            assert_eq!(squares, (0..12).collect::<Vec<usize>>());
+        }
+        // the orbits are {0, 3, 8, 11}, {1, 2, 9, 10}, {4, 7} and {5, 6}
+        assert_eq!(board.get_unique_moves(), vec![0, 1, 4, 5]);
+    }
+
+    #[test]
+    fn squares_are_parsed_in_every_format() {
+        let board = Board::new();
+        assert_eq!(board.parse_square("b2"), Ok(4));
+        assert_eq!(board.parse_square(" C1\n"), Ok(2));
+        assert_eq!(board.parse_square("2,3"), Ok(5));
+        assert_eq!(board.parse_square("2 3"), Ok(5));
+        assert_eq!(board.parse_square("3, 1"), Ok(6));
+        assert_eq!(board.parse_square("4"), Ok(4));
+        assert_eq!(board.parse_square("0"), Ok(0));
+
+        for off_board in ["d1", "a4", "a0", "9", "4,1", "1,0"] {
+            assert!(board.parse_square(off_board).unwrap_err().ends_with("is off the board"), "{}", off_board);
+        }
+        for garbage in ["", "zz", "b", "1,2,3", "x,y", "-1"] {
+            assert!(board.parse_square(garbage).is_err(), "{}", garbage);
         }
 
-        return None;
+        let board = Board::with_dimensions(7, 6, 4);
+        for square in 0..42 {
+            assert_eq!(board.parse_square(&board.square_name(square)), Ok(square));
+        }
     }
 }
@@ -0,0 +1,378 @@
+use std::time::Duration;
+
+use tttoe::board::Board;
+use tttoe::defines::*;
+use tttoe::uct::{SearchLimits, DEFAULT_ITERATIONS};
+
+pub const USAGE: &str = "\
+Usage: tttoe [command] [options]
+
+Commands:
+    play                 play against the engine (default)
+    analyze <position>   search a position given in board notation (\"X-O/-X-/--O x\")
+                         or as a move list (\"b2 a1\" or \"4,0\")
+    selfplay             let the engine play against itself
+    solve [<position>]   solve the position (default: the empty board) with the negamax solver
+    bench                measure search speed
+    tournament           play two players against each other (default: uct against uct
+                         with a different budget)
+    help                 print this message
+
+Options:
+    --size <W>x<H>       board size (default 3x3)
+    -k <K>               marks in a row needed to win (default: the board's shorter side)
+    -n, --iterations <N> search iterations per move (default 10000)
+    -t, --time <MS>      search time per move in milliseconds
+    --level <LEVEL>      engine strength instead of -n/-t: easy, medium, hard or expert
+    --human <x|o>        side played by the human (default x)
+    --first <human|engine>
+                         who makes the first move (default: whoever plays x)
+    --exploration <C>    exploration constant of the uct tree policy (default 1.41)
+    --seed <N>           seed the search for reproducible results
+    --format <text|json> output format (default text)
+    --bitboard           run bench on the bitboard implementation of the board
+    --games <N>          number of games for selfplay (default 10) and tournament (default 100)
+    --player <TYPE>      first player in a tournament: mcts, solver, database (3x3 only),
+                         random or heuristic (default mcts)
+    --opponent <TYPE>    second player in a tournament (default mcts)
+    --opponent-iterations <N>
+                         iterations of the second player in a tournament (default 1000)
+    --opponent-exploration <C>
+                         exploration constant of the second player (default: --exploration)";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Play,
+    Analyze(String),
+    Selfplay,
+    Solve(Option<String>),
+    Bench,
+    Tournament,
+    Help,
+}
+
+// Engine strength for interactive play, each level is a search budget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Level {
+    pub fn limits(&self) -> SearchLimits {
+        match self {
+            Level::Easy => SearchLimits::iterations(50),
+            Level::Medium => SearchLimits::iterations(1000),
+            Level::Hard => SearchLimits::iterations(DEFAULT_ITERATIONS),
+            Level::Expert => SearchLimits::iterations(200000).with_time(Duration::from_secs(2)),
+        }
+    }
+}
+
+// Kinds of player that can take part in a tournament
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerKind {
+    Mcts,
+    Solver,
+    Database,
+    Random,
+    Heuristic,
+}
+
+fn parse_player_kind(option: &str, value: Option<&String>) -> Result<PlayerKind, String> {
+    match value.map(|s| s.to_lowercase()).as_deref() {
+        Some("mcts") | Some("uct") => Ok(PlayerKind::Mcts),
+        Some("solver") => Ok(PlayerKind::Solver),
+        Some("database") => Ok(PlayerKind::Database),
+        Some("random") => Ok(PlayerKind::Random),
+        Some("heuristic") => Ok(PlayerKind::Heuristic),
+        _ => Err(format!("{} expects mcts, solver, database, random or heuristic", option)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub width: usize,
+    pub height: usize,
+    pub k: usize,
+    // --size and -k as given on the command line, None if left out. Positions
+    // in board notation carry their own dimensions, which have to agree.
+    pub given_size: Option<(usize, usize)>,
+    pub given_k: Option<usize>,
+    pub limits: SearchLimits,
+    // UCB1 exploration constant, None keeps the default
+    pub exploration: Option<f32>,
+    pub human: Mark,
+    // None lets X move first
+    pub human_first: Option<bool>,
+    pub seed: Option<u64>,
+    pub format: OutputFormat,
+    // number of games for selfplay (default 10) and tournament (default 100), each has its own default
+    pub games: Option<u32>,
+    // tournament players, `player` is A and `opponent` is B
+    pub player: PlayerKind,
+    pub opponent: PlayerKind,
+    pub opponent_iterations: u32,
+    // None uses the same constant as player A
+    pub opponent_exploration: Option<f32>,
+    // bench BitBoard instead of Board
+    pub bitboard: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            command: Command::Play,
+            width: ROWS,
+            height: ROWS,
+            k: ROWS,
+            given_size: None,
+            given_k: None,
+            limits: SearchLimits::default(),
+            exploration: None,
+            human: Mark::X,
+            human_first: None,
+            seed: None,
+            format: OutputFormat::Text,
+            games: None,
+            player: PlayerKind::Mcts,
+            opponent: PlayerKind::Mcts,
+            opponent_iterations: 1000,
+            opponent_exploration: None,
+            bitboard: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn empty_board(&self) -> Board {
+        Board::with_dimensions(self.width, self.height, self.k)
+    }
+
+    // Mark of the player making the first move in interactive play
+    pub fn first_player(&self) -> Mark {
+        let engine = match self.human {
+            Mark::X => Mark::O,
+            _ => Mark::X,
+        };
+
+        match self.human_first {
+            Some(true) => self.human,
+            Some(false) => engine,
+            None => Mark::X,
+        }
+    }
+
+    // Board from a position given on the command line: either board notation
+    // ("X-O/-X-/--O x", see Board::to_notation) or a move list ("b2 a1",
+    // "4,0,8") played on the empty board. -k applies to board notation that
+    // leaves out the win length.
+    pub fn parse_position(&self, position: &str) -> Result<Board, String> {
+        if position.contains('/') {
+            return self.parse_notation(position);
+        }
+
+        let mut board = self.empty_board();
+        board.play_moves(position)?;
+        Ok(board)
+    }
+
+    // Board notation, checked against --size and -k
+    fn parse_notation(&self, notation: &str) -> Result<Board, String> {
+        let board = notation.parse::<Board>()?;
+        if let Some((width, height)) = self.given_size {
+            if (width, height) != (board.width(), board.height()) {
+                return Err(format!("The position is {}x{}, but --size is {}x{}", board.width(), board.height(), width, height));
+            }
+        }
+
+        match self.given_k {
+            Some(k) if k != board.win_length() => {
+                if notation.split_whitespace().count() == 3 {
+                    return Err(format!("The position has a win length of {}, but -k is {}", board.win_length(), k));
+                }
+                format!("{} {}", board.to_notation(), k).parse()
+            }
+            _ => Ok(board),
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", option))?;
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", option, value))
+}
+
+// Parses the command line arguments (without the program name)
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut k = None;
+    let mut iterations = None;
+    let mut time = None;
+    let mut level = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => {
+                let value = args.next().ok_or("Missing value for --size")?;
+                let (width, height) = match value.split_once('x') {
+                    Some((width, height)) => (width.parse::<usize>(), height.parse::<usize>()),
+                    None => (value.parse::<usize>(), value.parse::<usize>()),
+                };
+                match (width, height) {
+                    (Ok(width), Ok(height)) if width > 0 && height > 0 => {
+                        options.width = width;
+                        options.height = height;
+                        options.given_size = Some((width, height));
+                    }
+                    _ => return Err(format!("Invalid board size: {}", value)),
+                }
+            }
+            "-k" | "--win-length" => k = Some(parse_number::<usize>(arg, args.next())?),
+            "-n" | "--iterations" => iterations = Some(parse_number::<u32>(arg, args.next())?),
+            "-t" | "--time" => time = Some(parse_number::<u64>(arg, args.next())?),
+            "--human" => {
+                options.human = match args.next().map(|s| s.to_lowercase()).as_deref() {
+                    Some("x") => Mark::X,
+                    Some("o") => Mark::O,
+                    _ => return Err(String::from("--human expects x or o")),
+                }
+            }
+            "--level" => {
+                level = match args.next().map(|s| s.to_lowercase()).as_deref() {
+                    Some("easy") => Some(Level::Easy),
+                    Some("medium") => Some(Level::Medium),
+                    Some("hard") => Some(Level::Hard),
+                    Some("expert") => Some(Level::Expert),
+                    _ => return Err(String::from("--level expects easy, medium, hard or expert")),
+                }
+            }
+            "--first" => {
+                options.human_first = match args.next().map(|s| s.as_str()) {
+                    Some("human") => Some(true),
+                    Some("engine") => Some(false),
+                    _ => return Err(String::from("--first expects human or engine")),
+                }
+            }
+            "--exploration" => options.exploration = Some(parse_number(arg, args.next())?),
+            "--seed" => options.seed = Some(parse_number(arg, args.next())?),
+            "--format" => {
+                options.format = match args.next().map(|s| s.as_str()) {
+                    Some("text") => OutputFormat::Text,
+                    Some("json") => OutputFormat::Json,
+                    _ => return Err(String::from("--format expects text or json")),
+                }
+            }
+            "--games" => options.games = Some(parse_number(arg, args.next())?),
+            "--player" => options.player = parse_player_kind(arg, args.next())?,
+            "--opponent" => options.opponent = parse_player_kind(arg, args.next())?,
+            "--opponent-iterations" => options.opponent_iterations = parse_number(arg, args.next())?,
+            "--opponent-exploration" => options.opponent_exploration = Some(parse_number(arg, args.next())?),
+            "--bitboard" => options.bitboard = true,
+            "-h" | "--help" => options.command = Command::Help,
+            // board notation can start with an empty square, e.g. "---/-X-/--- o"
+            _ if arg.starts_with('-') && arg.len() > 1 && !arg.contains('/') => return Err(format!("Unknown option: {}", arg)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    options.given_k = k;
+    options.k = k.unwrap_or_else(|| options.width.min(options.height));
+
+    // building a PositionDatabase enumerates every reachable position
+    let database_player = options.player == PlayerKind::Database || options.opponent == PlayerKind::Database;
+    if database_player && options.width * options.height > BOARD_SIZE {
+        return Err(format!("The database player needs a board of at most {} squares", BOARD_SIZE));
+    }
+
+    options.limits = match (iterations, time) {
+        (Some(iterations), Some(time)) => SearchLimits::iterations(iterations).with_time(Duration::from_millis(time)),
+        (Some(iterations), None) => SearchLimits::iterations(iterations),
+        (None, Some(time)) => SearchLimits::time(Duration::from_millis(time)),
+        (None, None) => level.map_or_else(SearchLimits::default, |level| level.limits()),
+    };
+
+    if options.command == Command::Help {
+        return Ok(options);
+    }
+
+    let mut positional = positional.into_iter();
+    options.command = match positional.next().as_deref() {
+        None | Some("play") => Command::Play,
+        Some("analyze") => Command::Analyze(positional.next().ok_or("analyze needs a position, e.g. \"X-O/-X-/--O x\"")?),
+        Some("selfplay") => Command::Selfplay,
+        Some("solve") => Command::Solve(positional.next()),
+        Some("bench") => Command::Bench,
+        Some("tournament") => Command::Tournament,
+        Some("help") => Command::Help,
+        Some(command) => return Err(format!("Unknown command: {}", command)),
+    };
+
+    if let Some(arg) = positional.next() {
+        return Err(format!("Unexpected argument: {}", arg));
+    }
+
+    // a position in board notation has its own size, -k is checked against that
+    let notation = match &options.command {
+        Command::Analyze(position) | Command::Solve(Some(position)) => position.contains('/'),
+        _ => false,
+    };
+    let own_size = notation && options.given_size.is_none();
+    if !own_size && (options.k == 0 || options.k > options.width.max(options.height)) {
+        return Err(format!("Win length {} doesn't fit on a {}x{} board", options.k, options.width, options.height));
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_5X5: &str = "-----/-----/-----/-----/----- x";
+
+    fn parse(args: &str) -> Result<Options, String> {
+        parse_args(&args.split_whitespace().map(String::from).collect::<Vec<String>>())
+    }
+
+    fn options_with(given_size: Option<(usize, usize)>, given_k: Option<usize>) -> Options {
+        Options { given_size, given_k, ..Options::default() }
+    }
+
+    #[test]
+    fn board_notation_follows_win_length() {
+        let board = options_with(None, None).parse_position(EMPTY_5X5).unwrap();
+        assert_eq!((board.width(), board.height(), board.win_length()), (5, 5, 5));
+        let board = options_with(None, Some(4)).parse_position(EMPTY_5X5).unwrap();
+        assert_eq!(board.win_length(), 4);
+        let board = options_with(Some((5, 5)), Some(3)).parse_position("-----/-----/-----/-----/----- x 3").unwrap();
+        assert_eq!(board.win_length(), 3);
+    }
+
+    #[test]
+    fn board_notation_conflicts_are_errors() {
+        assert!(options_with(None, Some(4)).parse_position("-----/-----/-----/-----/----- x 3").is_err());
+        assert!(options_with(Some((4, 4)), None).parse_position(EMPTY_5X5).is_err());
+        assert!(options_with(None, Some(6)).parse_position(EMPTY_5X5).is_err());
+    }
+
+    #[test]
+    fn win_length_is_checked_against_the_notation() {
+        let options = parse("analyze -----/-----/-----/-----/----- -k 4").unwrap();
+        assert_eq!(options.given_k, Some(4));
+        assert!(parse("analyze b2 -k 4").is_err());
+        assert!(parse("-k 4").is_err());
+        assert!(parse("--size 5 -k 4").is_ok());
+    }
+}
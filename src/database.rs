@@ -0,0 +1,330 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use crate::board::Board;
+use crate::defines::*;
+use crate::game_state::GameState;
+use crate::solver::Solver;
+
+// Identifies the binary format written by PositionDatabase::to_bytes
+const MAGIC: &[u8; 4] = b"TTDB";
+const VERSION: u8 = 1;
+
+// Solved value of one position, stored in its canonical orientation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseEntry {
+    // WIN, DRAW or LOSS for the player to move
+    pub value: f32,
+    // all moves that keep that value
+    pub best_moves: Vec<usize>,
+}
+
+// Every position reachable from a starting board, solved. Positions are stored
+// once per group of symmetric positions (see Board::canonical), e.g. the 5478
+// legal tic-tac-toe positions are stored as 765 entries.
+#[derive(Debug, Clone)]
+pub struct PositionDatabase {
+    width: usize,
+    height: usize,
+    k: usize,
+    // number of distinct (non-canonical) positions reachable from the start
+    reachable_positions: usize,
+    entries: HashMap<u64, DatabaseEntry>,
+    // canonical boards of all entries, in the order they were found
+    boards: Vec<Board>,
+}
+
+impl PositionDatabase {
+    // Enumerates and solves every position reachable from `start`. Only
+    // feasible for small boards such as the default 3x3.
+    pub fn build(start: &Board) -> PositionDatabase {
+        let mut database = PositionDatabase {
+            width: start.width(),
+            height: start.height(),
+            k: start.win_length(),
+            reachable_positions: 0,
+            entries: HashMap::new(),
+            boards: Vec::new(),
+        };
+
+        let mut seen = HashSet::new();
+        let mut solver = Solver::new();
+        let mut board = start.clone();
+        database.enumerate(&mut board, &mut seen, &mut solver);
+        database.reachable_positions = seen.len();
+
+        database
+    }
+
+    fn enumerate(&mut self, board: &mut Board, seen: &mut HashSet<u64>, solver: &mut Solver) {
+        if !seen.insert(board.hash()) {
+            return;
+        }
+
+        let (canonical, _) = board.canonical();
+        if let Entry::Vacant(vacant) = self.entries.entry(canonical.hash()) {
+            let solution = solver.solve(&canonical);
+            vacant.insert(DatabaseEntry { value: solution.value, best_moves: solution.best_moves });
+            self.boards.push(canonical);
+        }
+
+        for move_ in board.get_moves() {
+            board.make_move(move_);
+            self.enumerate(board, seen, solver);
+            board.take_move();
+        }
+    }
+
+    // Number of distinct positions reachable from the starting board
+    pub fn reachable_positions(&self) -> usize {
+        self.reachable_positions
+    }
+
+    // Number of stored (canonical) positions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Solved value of `board`, with the best moves mapped to the orientation
+    // of `board`. None if the position isn't in the database.
+    pub fn lookup(&self, board: &Board) -> Option<DatabaseEntry> {
+        if (board.width(), board.height(), board.win_length()) != (self.width, self.height, self.k) {
+            return None;
+        }
+
+        let (canonical, symmetry) = board.canonical();
+        let entry = self.entries.get(&canonical.hash())?;
+        let inverse = Board::inverse_symmetry(symmetry);
+
+        let mut best_moves = entry.best_moves.
+            iter().
+            map(|m| canonical.transform_square(*m, inverse)).
+            collect::<Vec<usize>>();
+        best_moves.sort_unstable();
+
+        Some(DatabaseEntry { value: entry.value, best_moves })
+    }
+
+    // Compact binary table: header (magic, version, width, height, k, reachable
+    // positions, entry count) followed by one record per entry (ordered by hash)
+    // holding the canonical hash, the value (0 loss, 1 draw, 2 win) and the best moves.
+    // All integers are little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.width as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.k as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.reachable_positions as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        // sorted by hash so the same database always gives the same bytes
+        let mut hashes = self.entries.keys().cloned().collect::<Vec<u64>>();
+        hashes.sort_unstable();
+        for hash in hashes {
+            let entry = &self.entries[&hash];
+            bytes.extend_from_slice(&hash.to_le_bytes());
+            bytes.push((entry.value * 2.0) as u8);
+            bytes.push(entry.best_moves.len() as u8);
+            for move_ in entry.best_moves.iter() {
+                bytes.extend_from_slice(&(*move_ as u16).to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    // Reads a table written by to_bytes. The canonical boards aren't stored in
+    // the binary format, so a loaded database can be used for lookups and
+    // written back as binary but its JSON export has no boards.
+    pub fn from_bytes(bytes: &[u8]) -> Result<PositionDatabase, String> {
+        let mut reader = ByteReader { bytes, offset: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err(String::from("Not a position database"));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("Unsupported database version: {}", version));
+        }
+
+        let width = reader.read_u16()? as usize;
+        let height = reader.read_u16()? as usize;
+        let k = reader.read_u16()? as usize;
+        let reachable_positions = reader.read_u32()? as usize;
+        let count = reader.read_u32()? as usize;
+
+        let mut entries = HashMap::with_capacity(count);
+        for _i in 0..count {
+            let hash = reader.read_u64()?;
+            let value = match reader.take(1)?[0] {
+                0 => LOSS,
+                1 => DRAW,
+                2 => WIN,
+                other => return Err(format!("Invalid value: {}", other)),
+            };
+            let move_count = reader.take(1)?[0] as usize;
+            let mut best_moves = Vec::with_capacity(move_count);
+            for _j in 0..move_count {
+                best_moves.push(reader.read_u16()? as usize);
+            }
+
+            entries.insert(hash, DatabaseEntry { value, best_moves });
+        }
+
+        if reader.offset != bytes.len() {
+            return Err(String::from("Trailing data after the last entry"));
+        }
+
+        Ok(PositionDatabase { width, height, k, reachable_positions, entries, boards: Vec::new() })
+    }
+
+    // JSON export, one object per canonical position with its squares (row by
+    // row, X/O/-), the player to move, the value and the best moves
+    pub fn to_json(&self) -> String {
+        let positions = self.boards.
+            iter().
+            map(|board| {
+                let entry = &self.entries[&board.hash()];
+                let squares = (0..board.width() * board.height()).
+                    map(|idx| match board.get_mark(idx) {
+                        Mark::X => 'X',
+                        Mark::O => 'O',
+                        Mark::NoPlayer => '-',
+                    }).
+                    collect::<String>();
+                let to_move = board.update_player_jm(board.player_just_moved);
+                let best_moves = entry.best_moves.iter().map(|m| m.to_string()).collect::<Vec<String>>();
+
+                format!("    {{\"hash\": \"{:016x}\", \"squares\": \"{}\", \"to_move\": \"{:?}\", \"value\": {}, \"best_moves\": [{}]}}",
+                        board.hash(), squares, to_move, entry.value, best_moves.join(", "))
+            }).
+            collect::<Vec<String>>();
+
+        format!("{{\n  \"width\": {},\n  \"height\": {},\n  \"k\": {},\n  \"reachable_positions\": {},\n  \"positions\": [\n{}\n  ]\n}}\n",
+                self.width, self.height, self.k, self.reachable_positions, positions.join(",\n"))
+    }
+}
+
+// Reads little endian integers from a byte slice
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.offset + count > self.bytes.len() {
+            return Err(String::from("Unexpected end of data"));
+        }
+
+        let slice = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(slice)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let mut buffer = [0; 2];
+        buffer.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let mut buffer = [0; 4];
+        buffer.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::*;
+    use crate::game::winner;
+
+    // Database of the standard game, built once for all tests
+    fn database() -> &'static PositionDatabase {
+        static DATABASE: OnceLock<PositionDatabase> = OnceLock::new();
+        DATABASE.get_or_init(|| PositionDatabase::build(&Board::new()))
+    }
+
+    // Calls `visit` once for every distinct position reachable from `board`
+    fn for_each_position(board: &mut Board, seen: &mut HashSet<u64>, visit: &mut dyn FnMut(&Board)) {
+        if !seen.insert(board.hash()) {
+            return;
+        }
+
+        visit(board);
+        for move_ in board.get_moves() {
+            board.make_move(move_);
+            for_each_position(board, seen, visit);
+            board.take_move();
+        }
+    }
+
+    #[test]
+    fn counts_positions_of_tic_tac_toe() {
+        assert_eq!(database().reachable_positions(), 5478);
+        assert_eq!(database().len(), 765);
+    }
+
+    #[test]
+    fn lookups_match_the_solver() {
+        let mut solver = Solver::new();
+        let mut positions = 0;
+        for_each_position(&mut Board::new(), &mut HashSet::new(), &mut |board| {
+            let entry = database().lookup(board).expect("Reachable position missing");
+            let mut solution = solver.solve(board);
+            solution.best_moves.sort_unstable();
+            assert_eq!((entry.value, &entry.best_moves), (solution.value, &solution.best_moves), "{}", board);
+            positions += 1;
+        });
+        assert_eq!(positions, 5478);
+    }
+
+    #[test]
+    fn finished_games_match_get_result() {
+        // X wins 626 of the finished games, O 316 and 16 are drawn
+        let (mut x_wins, mut o_wins, mut draws) = (0, 0, 0);
+        for_each_position(&mut Board::new(), &mut HashSet::new(), &mut |board| {
+            if !board.get_moves().is_empty() {
+                return;
+            }
+
+            let to_move = board.update_player_jm(board.player_just_moved);
+            let entry = database().lookup(board).expect("Finished game missing");
+            assert_eq!(Some(entry.value), board.get_result(to_move), "{}", board);
+            assert!(entry.best_moves.is_empty());
+
+            match winner(board) {
+                Mark::X => x_wins += 1,
+                Mark::O => o_wins += 1,
+                Mark::NoPlayer => draws += 1,
+            }
+        });
+
+        assert_eq!((x_wins, o_wins, draws), (626, 316, 16));
+    }
+
+    #[test]
+    fn binary_format_round_trips() {
+        let loaded = PositionDatabase::from_bytes(&database().to_bytes()).expect("Valid database");
+        assert_eq!(loaded.len(), 765);
+        assert_eq!(loaded.reachable_positions(), 5478);
+
+        let board = "X--/-O-/--- x".parse::<Board>().expect("Valid position");
+        assert_eq!(loaded.lookup(&board), database().lookup(&board));
+    }
+}
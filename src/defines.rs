@@ -1,3 +1,4 @@
+// Default board dimensions (classic 3x3 tic-tac-toe, 3 in a row wins)
 pub const ROWS: usize = 3;
 pub const BOARD_SIZE: usize = ROWS*ROWS;
 
@@ -13,56 +14,55 @@ pub const LOSS: f32 = 0.0;
 pub const DRAW: f32 = 0.5;
 pub const WIN: f32 = 1.0;
 
-pub fn get_result_lines() -> Vec<Vec<Vec<usize>>> {
-    let col_vec = get_column_vector();
-    let row_vec = get_row_vector(&col_vec);
-    let diagonal_vec = get_diagonals(&row_vec);
+// Returns every segment of k consecutive squares that wins the game on a
+// width x height board, grouped as [columns, rows, diagonals].
+// Squares are indexed row by row, i.e. index = row * width + col
+pub fn get_result_lines(width: usize, height: usize, k: usize) -> Vec<Vec<Vec<usize>>> {
+    let col_vec = get_column_vector(width, height, k);
+    let row_vec = get_row_vector(width, height, k);
+    let diagonal_vec = get_diagonals(width, height, k);
 
-    let result_lines = vec![col_vec, row_vec, diagonal_vec];
-    result_lines
+    vec![col_vec, row_vec, diagonal_vec]
 }
 
-fn get_row_vector(col_vec: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
-    let mut row_vec: Vec<Vec<usize>> = vec![vec![0; ROWS]; ROWS];
+// Collects all k-long segments starting from any square and moving in the
+// direction (d_col, d_row) which fit entirely on the board
+fn get_segments(width: usize, height: usize, k: usize, d_col: isize, d_row: isize) -> Vec<Vec<usize>> {
+    let mut segments = Vec::new();
 
-    for i in 0..ROWS {
-        for j in 0..ROWS {
-            // performs the equivalent of zip on a list of lists in python
-            // [[1, 2], [3, 4]] -> [[1, 3], [2, 4]]
-            row_vec[i][j] = col_vec[j][i];
+    for row in 0..height as isize {
+        for col in 0..width as isize {
+            let end_col = col + d_col * (k as isize - 1);
+            let end_row = row + d_row * (k as isize - 1);
+            if end_col < 0 || end_col >= width as isize || end_row < 0 || end_row >= height as isize {
+                continue;
+            }
+
+            let segment = (0..k as isize).
+                map(|i| ((row + d_row * i) * width as isize + col + d_col * i) as usize).
+                collect::<Vec<usize>>();
+            segments.push(segment);
         }
     }
 
-    row_vec
+    segments
 }
 
-fn get_column_vector() -> Vec<Vec<usize>> {
-    let mut col_vec: Vec<Vec<usize>> = vec![vec![0; ROWS]; ROWS];
+fn get_row_vector(width: usize, height: usize, k: usize) -> Vec<Vec<usize>> {
+    // i.e. 0-1-2, 3-4-5 etc. for a 3x3 board
+    get_segments(width, height, k, 1, 0)
+}
 
+fn get_column_vector(width: usize, height: usize, k: usize) -> Vec<Vec<usize>> {
     // take every nth element from board starting from each of the first row columns
-    // i.e. 0-3-6, 1-4-7 etc.
-    for i in 0..ROWS {
-        let v_temp = (i..BOARD_SIZE).
-            step_by(ROWS).
-            collect::<Vec<usize>>();
-
-        col_vec[i] = v_temp;
-    }
-
-    col_vec
+    // i.e. 0-3-6, 1-4-7 etc. for a 3x3 board
+    get_segments(width, height, k, 0, 1)
 }
 
-
-fn get_diagonals(row_vec: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
-    let mut left_diagonal: Vec<usize> = vec![0; ROWS];
-    let mut right_diagonal: Vec<usize> = vec![0; ROWS];
-
-    for i in 0..ROWS {
-        left_diagonal[i] = row_vec[i][i]; // left diagonal -> 0,0| 1,1| 2,2
-        right_diagonal[i] = row_vec[i][ROWS-i-1] // right diagonal 0,2 | 1,1| 2,0 .
-        // Additional -1 is needed to convert from size to index
-    }
-
-    let diagonals = vec![left_diagonal, right_diagonal];
+fn get_diagonals(width: usize, height: usize, k: usize) -> Vec<Vec<usize>> {
+    // left diagonals -> 0,0| 1,1| 2,2 and right diagonals 0,2 | 1,1| 2,0,
+    // including the short ones that don't touch the board corners
+    let mut diagonals = get_segments(width, height, k, 1, 1);
+    diagonals.extend(get_segments(width, height, k, -1, 1));
     diagonals
 }
@@ -0,0 +1,144 @@
+use crate::node_1::Proof;
+use crate::search_result::MoveStats;
+
+// Confidence weight used by FinalMovePolicy::SecureChild when none is given
+pub const SECURE_CHILD_CONSTANT: f32 = 1.0;
+
+// How the move to play is picked from the root children once the search is over
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FinalMovePolicy {
+    // Highest mean value (wins / visits). Can pick a barely visited child that got lucky
+    MaxChild,
+    // Most visited child
+    #[default]
+    RobustChild,
+    // Highest lower confidence bound: mean - A / sqrt(visits), with A the given constant
+    SecureChild(f32),
+    // Child that is both the most visited and has the highest mean. The search
+    // is extended while the two disagree, falling back to the robust child if
+    // they still disagree when the extension runs out
+    MaxRobustChild,
+}
+
+// Index of the first element with the highest key
+fn argmax<M>(moves: &[MoveStats<M>], key: impl Fn(&MoveStats<M>) -> f32) -> usize {
+    let mut best_index = 0;
+    for (index, stats) in moves.iter().enumerate() {
+        if key(stats) > key(&moves[best_index]) {
+            best_index = index;
+        }
+    }
+
+    best_index
+}
+
+fn max_child<M>(moves: &[MoveStats<M>]) -> usize {
+    argmax(moves, |stats| stats.mean)
+}
+
+fn robust_child<M>(moves: &[MoveStats<M>]) -> usize {
+    argmax(moves, |stats| stats.visits)
+}
+
+fn secure_child<M>(moves: &[MoveStats<M>], confidence: f32) -> usize {
+    argmax(moves, |stats| stats.mean - confidence / stats.visits.sqrt())
+}
+
+// True if the max child and the robust child are the same move
+pub fn max_robust_agree<M>(moves: &[MoveStats<M>]) -> bool {
+    max_child(moves) == robust_child(moves)
+}
+
+// Picks the move to play out of the statistics of the root moves. Moves
+// proven by an MCTS-Solver override the policy: the quickest proven win is
+// always played and proven losses are avoided while there is anything else.
+pub fn select_final_move<M: Copy>(moves: &[MoveStats<M>], policy: FinalMovePolicy) -> M {
+    assert!(!moves.is_empty(), "No moves to select from");
+
+    if let Some(win) = moves.iter().filter(|m| m.proof == Some(Proof::Win)).min_by_key(|m| m.proof_depth) {
+        return win.move_;
+    }
+
+    let not_lost = moves.iter().filter(|m| m.proof != Some(Proof::Loss)).cloned().collect::<Vec<MoveStats<M>>>();
+    if not_lost.is_empty() {
+        // everything loses, so delay the loss as long as possible
+        return moves.iter().max_by_key(|m| m.proof_depth).unwrap().move_;
+    }
+    let moves = &not_lost[..];
+
+    let index = match policy {
+        FinalMovePolicy::MaxChild => max_child(moves),
+        FinalMovePolicy::RobustChild => robust_child(moves),
+        FinalMovePolicy::SecureChild(confidence) => secure_child(moves, confidence),
+        // when the extended search still ends in disagreement the robust child is the safer pick
+        FinalMovePolicy::MaxRobustChild => robust_child(moves),
+    };
+
+    moves[index].move_
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(move_: usize, visits: f32, mean: f32) -> MoveStats<usize> {
+        MoveStats { move_, visits, wins: visits * mean, mean, proof: None, proof_depth: 0 }
+    }
+
+    fn proven(move_: usize, visits: f32, mean: f32, proof: Proof, proof_depth: u32) -> MoveStats<usize> {
+        MoveStats { proof: Some(proof), proof_depth, ..stats(move_, visits, mean) }
+    }
+
+    // Move 0 is the most visited, move 1 has the best mean after two lucky
+    // visits and move 2 has the highest lower confidence bound
+    fn disagreeing_moves() -> Vec<MoveStats<usize>> {
+        vec![stats(0, 100.0, 0.6), stats(1, 2.0, 0.9), stats(2, 80.0, 0.7)]
+    }
+
+    #[test]
+    fn policies_pick_different_moves() {
+        let moves = disagreeing_moves();
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::MaxChild), 1);
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::RobustChild), 0);
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::SecureChild(SECURE_CHILD_CONSTANT)), 2);
+    }
+
+    #[test]
+    fn max_robust_falls_back_to_robust_child() {
+        let moves = disagreeing_moves();
+        assert!(!max_robust_agree(&moves));
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::MaxRobustChild), 0);
+
+        let agreeing = vec![stats(0, 100.0, 0.6), stats(1, 20.0, 0.5)];
+        assert!(max_robust_agree(&agreeing));
+    }
+
+    #[test]
+    fn quickest_proven_win_overrides_policy() {
+        let mut moves = disagreeing_moves();
+        moves.push(proven(3, 1.0, 1.0, Proof::Win, 5));
+        moves.push(proven(4, 1.0, 1.0, Proof::Win, 3));
+
+        for policy in [FinalMovePolicy::MaxChild, FinalMovePolicy::RobustChild,
+                       FinalMovePolicy::SecureChild(SECURE_CHILD_CONSTANT), FinalMovePolicy::MaxRobustChild] {
+            assert_eq!(select_final_move(&moves, policy), 4);
+        }
+    }
+
+    #[test]
+    fn proven_loss_is_avoided() {
+        let mut moves = disagreeing_moves();
+        moves[0].proof = Some(Proof::Loss);
+        moves[1].proof = Some(Proof::Loss);
+
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::RobustChild), 2);
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::MaxChild), 2);
+    }
+
+    #[test]
+    fn longest_loss_is_played_when_everything_loses() {
+        let moves = vec![proven(0, 100.0, 0.0, Proof::Loss, 1), proven(1, 10.0, 0.0, Proof::Loss, 4),
+                         proven(2, 50.0, 0.0, Proof::Loss, 2)];
+        assert_eq!(select_final_move(&moves, FinalMovePolicy::RobustChild), 1);
+    }
+}
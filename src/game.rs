@@ -0,0 +1,48 @@
+use crate::board::Board;
+use crate::defines::*;
+use crate::game_state::GameState;
+use crate::player::Player;
+
+// Plays a game from `start` to the end, X moves are chosen by `player_x`
+// and O moves by `player_o`, and returns the final position (see winner()).
+// The game stops early, unfinished, when a player quits. With `verbose` the
+// board and every move are printed.
+pub fn play_game(start: &Board, player_x: &mut dyn Player, player_o: &mut dyn Player, verbose: bool) -> Board {
+    let mut board = start.clone();
+
+    while board.get_result(board.player_just_moved).is_none() {
+        let player_to_move = board.update_player_jm(board.player_just_moved);
+        let player: &mut dyn Player = if player_to_move == Mark::X { &mut *player_x } else { &mut *player_o };
+
+        let move_ = match player.choose_move(&board) {
+            Some(move_) => move_,
+            None => {
+                if verbose {
+                    println!("{} ({:?}) quits", player.name(), player_to_move);
+                }
+                break;
+            }
+        };
+        board.make_move_safe(move_).
+            unwrap_or_else(|err| panic!("{} ({:?}) played an illegal move: {}", player.name(), player_to_move, err));
+
+        if verbose {
+            println!("{} ({:?}) plays {}", player.name(), player_to_move, move_);
+        }
+    }
+
+    if verbose {
+        println!("{}", board);
+    }
+
+    board
+}
+
+// Winner of a finished game, NoPlayer for a draw (or a game still in progress)
+pub fn winner(board: &Board) -> Mark {
+    match board.get_result(board.player_just_moved) {
+        Some(result) if result == WIN => board.player_just_moved,
+        Some(result) if result == LOSS => board.update_player_jm(board.player_just_moved),
+        _ => Mark::NoPlayer,
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use crate::defines::WIN;
+
+// Everything the UCT search needs to know about a game. Any two-player,
+// alternating-move game with a final result can be searched by implementing it.
+pub trait GameState: Clone {
+    type Move: Copy + PartialEq + fmt::Debug;
+    type Player: Copy + PartialEq + fmt::Debug;
+
+    // The player who made the last move (i.e. not the player to move)
+    fn player_just_moved(&self) -> Self::Player;
+
+    // All legal moves from this position, empty if the game is over
+    fn get_moves(&self) -> Vec<Self::Move>;
+
+    // only used for valid/verified moves, i.e. ones returned by get_moves()
+    fn make_move(&mut self, move_: Self::Move);
+
+    // Undo the last move made with make_move()
+    fn take_move(&mut self);
+
+    // Game result from the point of view of player_jm (WIN, DRAW, LOSS),
+    // None if the game is still in progress
+    fn get_result(&self, player_jm: Self::Player) -> Option<f32>;
+
+    // Hash identifying the position (including the player to move). Positions
+    // reached through different move orders must hash the same so the search
+    // can detect transpositions.
+    fn position_hash(&self) -> u64;
+
+    // Legal moves with symmetric duplicates removed, i.e. one move for every
+    // group of moves that lead to equivalent positions. The returned moves are
+    // real moves in the current orientation. Games without symmetry handling
+    // return all legal moves.
+    fn get_unique_moves(&self) -> Vec<Self::Move> {
+        self.get_moves()
+    }
+
+    // Moves that win the game on the spot for the player to move. The default
+    // tries every legal move.
+    fn winning_moves(&mut self) -> Vec<Self::Move> {
+        let mut winning_moves = Vec::new();
+        for move_ in self.get_moves() {
+            self.make_move(move_);
+            if self.get_result(self.player_just_moved()) == Some(WIN) {
+                winning_moves.push(move_);
+            }
+            self.take_move();
+        }
+
+        winning_moves
+    }
+
+    // Moves after which the opponent can't win with their next move, empty if
+    // every move allows a win. The default looks two moves ahead, games that
+    // can see the opponent's threats directly should override it.
+    fn safe_moves(&mut self) -> Vec<Self::Move> {
+        let mut safe_moves = Vec::new();
+        for move_ in self.get_moves() {
+            self.make_move(move_);
+            if self.winning_moves().is_empty() {
+                safe_moves.push(move_);
+            }
+            self.take_move();
+        }
+
+        safe_moves
+    }
+}
@@ -0,0 +1,19 @@
+pub mod bitboard;
+pub mod board;
+pub mod database;
+pub mod defines;
+pub mod final_move;
+pub mod game;
+pub mod game_state;
+pub mod node;
+pub mod node_1;
+pub mod parallel;
+pub mod player;
+pub mod rollout_policy;
+pub mod search_result;
+pub mod searcher;
+pub mod solver;
+pub mod tournament;
+pub mod tree_policy;
+pub mod uct;
+pub mod zobrist;
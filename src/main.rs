@@ -1,53 +1,244 @@
-mod board;
-mod defines;
-mod node;
-mod node_1;
-mod uct;
-use board::Board;
-use defines::{LOSS, WIN, DRAW};
-use std::io;
-// use node::{Arena, NodeData};
-use node_1::{Tree, Node};
-use uct::uct;
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-fn main() {
-    // Add generic trait implementation required to use the UCT algorithm
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use tttoe::bitboard::{BitBoard, MAX_BITBOARD_SIZE};
+use tttoe::board::Board;
+use tttoe::database::PositionDatabase;
+use tttoe::defines::*;
+use tttoe::game::{play_game, winner};
+use tttoe::game_state::GameState;
+use tttoe::player::{DatabasePlayer, HeuristicPlayer, MctsPlayer, Player, RandomPlayer, SolverPlayer};
+use tttoe::solver::Solver;
+use tttoe::tournament::{run_tournament, Sprt, TournamentConfig};
+use tttoe::tree_policy::Ucb1;
+use tttoe::uct::{uct_with_config, SearchConfig, SearchLimits};
+
+mod cli;
+mod play;
 
+use cli::{Command, Options, OutputFormat, PlayerKind};
+
+fn main() {
     // tttoe example here:
     // https://github.com/flofriday/tictactoe/blob/master/src/main.rs
-    // let node = Node::new_root(&b);
-    // let b = Board::new();
-    // let mut tree = Tree::new(&b);
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match &options.command {
+        Command::Play => {
+            play::play_user_game(&options);
+            Ok(())
+        }
+        Command::Analyze(position) => analyze(&options, position),
+        Command::Selfplay => {
+            selfplay(&options);
+            Ok(())
+        }
+        Command::Solve(position) => solve(&options, position.as_deref().unwrap_or("")),
+        Command::Bench => bench(&options),
+        Command::Tournament => {
+            play_tournament(&options);
+            Ok(())
+        }
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+// Search config built from the command line options. `offset` is added to
+// the seed so that players in the same run don't share their random numbers.
+pub fn search_config<G: GameState>(options: &Options, offset: u64) -> SearchConfig<G> {
+    let mut config = SearchConfig {
+        limits: options.limits,
+        seed: options.seed.map(|seed| seed.wrapping_add(offset)),
+        ..SearchConfig::default()
+    };
+    if let Some(exploration) = options.exploration {
+        config.tree_policy = Arc::new(Ucb1 { exploration });
+    }
+
+    config
+}
+
+fn winner_name(winner: Mark) -> &'static str {
+    match winner {
+        Mark::X => "X",
+        Mark::O => "O",
+        Mark::NoPlayer => "draw",
+    }
+}
+
+fn value_name(value: f32) -> &'static str {
+    if value == WIN {
+        "win"
+    } else if value == LOSS {
+        "loss"
+    } else {
+        "draw"
+    }
+}
+
+fn analyze(options: &Options, position: &str) -> Result<(), String> {
+    let board = options.parse_position(position)?;
+    let result = uct_with_config(&board, &search_config(options, 0)).ok_or("The game is already over")?;
+    match options.format {
+        OutputFormat::Text => println!("{}\n{}", board, result),
+        OutputFormat::Json => println!("{}", result.to_json()),
+    }
+
+    Ok(())
+}
+
+fn selfplay(options: &Options) {
+    let games = options.games.unwrap_or(10);
+    for game in 0..games {
+        let seed_offset = 2 * game as u64;
+        let mut player_x = MctsPlayer::new(search_config(options, seed_offset));
+        let mut player_o = MctsPlayer::new(search_config(options, seed_offset + 1));
+        let board = play_game(&options.empty_board(), &mut player_x, &mut player_o, false);
+
+        let moves = board.history().iter().map(|m| m.to_string()).collect::<Vec<String>>();
+        match options.format {
+            OutputFormat::Text => println!("Game {}: {} -> {} ({})",
+                                           game + 1, board.to_move_list(), winner_name(winner(&board)), board.to_notation()),
+            OutputFormat::Json => println!("{{\"game\": {}, \"moves\": [{}], \"position\": \"{}\", \"winner\": \"{}\"}}",
+                                           game + 1, moves.join(", "), board.to_notation(), winner_name(winner(&board))),
+        }
+    }
+}
 
-    play_user_game();
-    // let node1 = arena.new_node(NodeData::default());
-    // let node2 = arena.new_node(NodeData::default());
+fn solve(options: &Options, position: &str) -> Result<(), String> {
+    let board = options.parse_position(position)?;
+    let mut solver = Solver::new();
+    let start_time = Instant::now();
+    let solution = solver.solve(&board);
+    let elapsed = start_time.elapsed();
 
-    // arena.add_child(node1, node2);
+    let best_moves = solution.best_moves.iter().map(|m| m.to_string()).collect::<Vec<String>>();
+    match options.format {
+        OutputFormat::Text => {
+            println!("{}", board);
+            println!("Value for {:?}: {}", board.update_player_jm(board.player_just_moved), value_name(solution.value));
+            println!("Best moves: {}", best_moves.join(" "));
+            println!("{} nodes in {:?}", solver.nodes, elapsed);
+        }
+        OutputFormat::Json => println!("{{\"value\": \"{}\", \"best_moves\": [{}], \"nodes\": {}, \"elapsed_ms\": {}}}",
+                                       value_name(solution.value), best_moves.join(", "), solver.nodes, elapsed.as_millis()),
+    }
 
-    // println!("{:?}", tree);
+    Ok(())
 }
 
-fn play_user_game() {
-    let mut b = Board::new();
+// Plays `count` uniformly random games from `board`, returns the time taken
+fn random_playouts<G: GameState>(board: &G, count: u32, rng: &mut StdRng) -> Duration {
+    let start_time = Instant::now();
+    let mut state = board.clone();
+    for _ in 0..count {
+        let mut moves_played = 0;
+        while let Some(move_) = state.get_moves().choose(rng) {
+            state.make_move(*move_);
+            moves_played += 1;
+        }
+        for _ in 0..moves_played {
+            state.take_move();
+        }
+    }
+
+    start_time.elapsed()
+}
+
+// Searches the empty board with the configured limits, then plays as many
+// random games as the search had iterations, and reports the speed of both
+fn bench_board<G: GameState>(options: &Options, board: &G, name: &str) -> Result<(), String> {
+    let result = uct_with_config(board, &search_config(options, 0)).ok_or("The game is already over")?;
+    let iterations_per_second = result.iterations as f64 / result.elapsed.as_secs_f64();
+
+    let mut rng = StdRng::seed_from_u64(options.seed.unwrap_or(0));
+    let playout_time = random_playouts(board, result.iterations, &mut rng);
+    let playouts_per_second = result.iterations as f64 / playout_time.as_secs_f64();
 
-    while let None = b.get_result(b.player_just_moved) {
-        println!("{}", uct(&b, 10000));
+    match options.format {
+        OutputFormat::Text => {
+            println!("{} {}x{} (k = {}): {} iterations, {} nodes in {:?} -> {:.0} iterations/s",
+                     name, options.width, options.height, options.k,
+                     result.iterations, result.nodes, result.elapsed, iterations_per_second);
+            println!("{} random playouts in {:?} -> {:.0} playouts/s", result.iterations, playout_time, playouts_per_second);
+        }
+        OutputFormat::Json => println!("{{\"board\": \"{}\", \"width\": {}, \"height\": {}, \"k\": {}, \"iterations\": {}, \"nodes\": {}, \"elapsed_ms\": {}, \"iterations_per_second\": {:.0}, \"playouts_per_second\": {:.0}}}",
+                                       name, options.width, options.height, options.k,
+                                       result.iterations, result.nodes, result.elapsed.as_millis(), iterations_per_second, playouts_per_second),
+    }
+
+    Ok(())
+}
 
-        let moves = b.get_moves();
-        println!("{}", b);
-        println!("Enter move (available: {:?})", moves);
-        let mut input_move = String::new();
-        io::stdin().read_line(&mut input_move).expect("Failed to read line");
-        let move_: u8 = input_move.trim().parse().expect("Please type a positive number!");
+fn bench(options: &Options) -> Result<(), String> {
+    if options.bitboard {
+        if options.width * options.height > MAX_BITBOARD_SIZE {
+            return Err(format!("A bitboard has at most {} squares", MAX_BITBOARD_SIZE));
+        }
+        bench_board(options, &BitBoard::with_dimensions(options.width, options.height, options.k), "bitboard")
+    } else {
+        bench_board(options, &options.empty_board(), "board")
+    }
+}
 
-        b.make_move_safe(move_ as usize).unwrap();
+// Tournament player of the given kind, uct players search with `config` and
+// the others take their seed from it
+fn tournament_player(kind: PlayerKind, config: SearchConfig<Board>, start: &Board) -> Box<dyn Player> {
+    match kind {
+        PlayerKind::Mcts => Box::new(MctsPlayer::new(config)),
+        PlayerKind::Solver => Box::new(SolverPlayer::new(config.seed)),
+        PlayerKind::Database => Box::new(DatabasePlayer::new(PositionDatabase::build(start), config.seed)),
+        PlayerKind::Random => Box::new(RandomPlayer::new(config.seed)),
+        PlayerKind::Heuristic => Box::new(HeuristicPlayer::new(config.seed)),
     }
+}
 
-    println!("{}", b);
-    if let Some(winner) = b.get_result(b.player_just_moved) {
-        if winner == DRAW { println!("Draw") }
-        if winner == WIN { println!("Winner is {:?}", b.player_just_moved) }
-        if winner == LOSS { println!("Winner is {:?}", b.update_player_jm(b.player_just_moved)) }
+fn player_description(kind: PlayerKind, config: &SearchConfig<Board>) -> String {
+    match kind {
+        PlayerKind::Mcts => format!("uct {:?} {:?}", config.limits, config.tree_policy),
+        other => format!("{:?}", other).to_lowercase(),
     }
 }
+
+// --player with the configured limits (A) against --opponent with
+// --opponent-iterations (B), stopped early once the SPRT of 0 vs 50 Elo decides
+fn play_tournament(options: &Options) {
+    let config_a = search_config(options, 0);
+    let mut config_b = SearchConfig { limits: SearchLimits::iterations(options.opponent_iterations), ..search_config(options, 1) };
+    if let Some(exploration) = options.opponent_exploration {
+        config_b.tree_policy = Arc::new(Ucb1 { exploration });
+    }
+
+    println!("{} (A) vs {} (B)", player_description(options.player, &config_a), player_description(options.opponent, &config_b));
+    let mut player_a = tournament_player(options.player, config_a, &options.empty_board());
+    let mut player_b = tournament_player(options.opponent, config_b, &options.empty_board());
+    let config = TournamentConfig {
+        games: options.games.unwrap_or(100),
+        start: options.empty_board(),
+        sprt: Some(Sprt::new(0.0, 50.0)),
+        ..TournamentConfig::default()
+    };
+
+    println!("{}", run_tournament(player_a.as_mut(), player_b.as_mut(), &config));
+}
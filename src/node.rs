@@ -2,6 +2,7 @@ use std::f32;
 
 use crate::defines::*;
 use crate::board::Board;
+use crate::game_state::GameState;
 
 #[derive(Debug, Copy, Clone)]
 pub struct NodeId {
@@ -17,8 +18,8 @@ pub struct NodeData {
     pub player_just_moved: Mark,
 }
 
-impl NodeData {
-    pub fn default() -> Self {
+impl Default for NodeData {
+    fn default() -> Self {
         Self { 
             move_: None, 
             wins: 0.0, 
@@ -27,10 +28,12 @@ impl NodeData {
             player_just_moved: Mark::O
         }
     }
+}
 
+impl NodeData {
     pub fn new(move_: Option<usize>, state: &Board) -> Self {
         Self {
-            move_: move_,
+            move_,
             wins: 0.0,
             visits: 0.0,
             untried_moves: state.get_moves(),
@@ -47,7 +50,7 @@ pub struct Node {
     pub data: NodeData,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Arena {
     pub nodes: Vec<Node>,
 }
@@ -67,7 +70,7 @@ impl Arena { // todo: consider adding .get() implementation that return mut ref
             id: new_node_id,
             parent: None,
             children: Vec::new(),
-            data: data,
+            data,
         });
 
         // Return the node identifier
@@ -131,11 +134,11 @@ impl Node {
         // Ni is the number of times the node has been visited,
         // N is the total number of times its parent has been visited
         (node.data.wins / node.data.visits) + 
-        (2.0 * (self.data.visits as f32).ln() / node.data.visits as f32).sqrt()
+        (2.0 * self.data.visits.ln() / node.data.visits).sqrt()
     }
 
     pub fn select_child(&self, arena: &Arena) -> NodeId {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             panic!("No children to select from")
         }
 
@@ -1,112 +1,386 @@
-use std::f32;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use crate::defines::*;
-use crate::board::Board;
+use crate::game_state::GameState;
+use crate::tree_policy::TreePolicy;
+
+// Game-theoretic value of a node proven by the search, from the point of
+// view of the node's player_just_moved
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Proof {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Proof {
+    pub fn from_result(result: f32) -> Proof {
+        if result == WIN {
+            Proof::Win
+        } else if result == LOSS {
+            Proof::Loss
+        } else {
+            Proof::Draw
+        }
+    }
+}
+
+// Adds `delta` to the f32 stored (as its bits) in `value`
+fn atomic_add_f32(value: &AtomicU32, delta: f32) {
+    let add = |bits: u32| Some((f32::from_bits(bits) + delta).to_bits());
+    value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, add).expect("Update never fails!");
+}
+
+// Visit statistics of a node or an edge. The counters are atomics, so threads
+// sharing one tree (see ParallelStrategy::Tree) update them through a shared
+// reference instead of locking the whole tree.
+#[derive(Debug, Default)]
+pub struct Stats {
+    visits: AtomicU32,
+    // f32 bits of the sum of results
+    wins: AtomicU32,
+    // f32 bits of the sum of squared results, used for variance-aware tree policies
+    sum_squares: AtomicU32,
+}
+
+impl Stats {
+    pub fn visits(&self) -> f32 {
+        self.visits.load(Ordering::Relaxed) as f32
+    }
+
+    pub fn wins(&self) -> f32 {
+        f32::from_bits(self.wins.load(Ordering::Relaxed))
+    }
+
+    pub fn sum_squares(&self) -> f32 {
+        f32::from_bits(self.sum_squares.load(Ordering::Relaxed))
+    }
+
+    // wins / visits, 0 before the first visit
+    pub fn score(&self) -> f32 {
+        let visits = self.visits();
+        if visits > 0.0 { self.wins() / visits } else { 0.0 }
+    }
+
+    pub fn update(&self, result: f32) {
+        self.visits.fetch_add(1, Ordering::Relaxed);
+        atomic_add_f32(&self.wins, result);
+        atomic_add_f32(&self.sum_squares, result * result);
+    }
+
+    // Counts a visit that hasn't returned a result yet as a loss, so parallel
+    // searchers sharing the tree are steered towards other nodes
+    pub fn add_virtual_loss(&self) {
+        self.visits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Undoes add_virtual_loss once the real result is known
+    pub fn revert_virtual_loss(&self) {
+        self.visits.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Clone for Stats {
+    fn clone(&self) -> Self {
+        Stats {
+            visits: AtomicU32::new(self.visits.load(Ordering::Relaxed)),
+            wins: AtomicU32::new(self.wins.load(Ordering::Relaxed)),
+            sum_squares: AtomicU32::new(self.sum_squares.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PartialEq for Stats {
+    fn eq(&self, other: &Self) -> bool {
+        (self.visits(), self.wins(), self.sum_squares()) == (other.visits(), other.wins(), other.sum_squares())
+    }
+}
+
+// Link from a node to one of its children. In a DAG a child can be reached
+// from several parents, each edge keeps the statistics of the visits made
+// through it and the prior of its move in the parent position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge<M> {
+    pub move_: M,
+    pub stats: Stats,
+    // prior probability of the move (see TreePolicy::prior)
+    pub prior: f32,
+}
+
+impl<M> Edge<M> {
+    pub fn new(move_: M, prior: f32) -> Self {
+        Edge { move_, stats: Stats::default(), prior }
+    }
+}
 
 // TODO: maybe split to 2 separate structs
 #[derive(Debug)]
-pub struct Node {
+pub struct Node<G: GameState> {
     pub index: usize,
-    pub parent: Option<usize>,
+    // A node has a single parent in a tree, but can have several in a
+    // DAG when its position is reached through different move orders
+    pub parents: Vec<usize>,
     pub children: Vec<usize>,
+    // edges[i] is the edge (and move) leading from this node to children[i]
+    pub edges: Vec<Edge<G::Move>>,
 
     // data
-    pub move_: Option<usize>,
-    pub wins: f32,
-    pub visits: f32,
-    pub score: f32,
-    pub untried_moves: Vec<usize>,
-    pub player_just_moved: Mark,
+    // move from the first parent that created this node (None for the root)
+    pub move_: Option<G::Move>,
+    // visits through all of the node's parents
+    pub stats: Stats,
+    pub untried_moves: Vec<G::Move>,
+    pub player_just_moved: G::Player,
+    // Set for terminal nodes and, when the tree runs as an MCTS-Solver, for
+    // nodes whose value follows from the proofs of their children
+    pub proof: Option<Proof>,
+    // number of plies until the end of the game under perfect play (for proven nodes)
+    pub proof_depth: u32,
 }
 
-impl Node {
-    pub fn new(index: usize, parent: Option<usize>, move_: Option<usize>, state: &Board) -> Self {
+impl<G: GameState> Node<G> {
+    pub fn new(index: usize, parent: Option<usize>, move_: Option<G::Move>, state: &G) -> Self {
+        let player_just_moved = state.player_just_moved();
+
         Self {
-            index: index,
-            parent: parent,
+            index,
+            parents: parent.into_iter().collect(),
             children: Vec::new(),
+            edges: Vec::new(),
 
-            move_: move_,
-            wins: 0.0,
-            visits: 0.0,
-            score: 0.0,
+            move_,
+            stats: Stats::default(),
             untried_moves: state.get_moves(),
-            player_just_moved: state.player_just_moved,
+            player_just_moved,
+            proof: state.get_result(player_just_moved).map(Proof::from_result),
+            proof_depth: 0,
         }
     }
 
-    pub fn update(&mut self, result: f32) {
-        self.visits += 1.0;
-        self.wins += result;
-        self.score = self.wins / self.visits;
-    }
-
-    
-    pub fn ucb1(&self, child_node: &Node) -> f32 {
-        // Implements UCB -> upper confidence boundary that helps select the most 
-        // promising child nodes
-        // Vi + sqrt( ln(N) / Ni ), where Vi is the estimated value of the node
-        // Ni is the number of times the node has been visited,
-        // N is the total number of times its parent has been visited
-        (child_node.score) + 
-        (2.0 * (self.visits as f32).ln() / child_node.visits as f32).sqrt()
+    // Move leading from this node to one of its children
+    pub fn child_move(&self, child_id: usize) -> G::Move {
+        let index = self.children.iter().position(|x| *x == child_id).expect("Not a child of this node!");
+        self.edges[index].move_
     }
 }
 
-#[derive(Debug, Default)]
-pub struct Tree {
-    arena: Vec<Node>,
+#[derive(Debug)]
+pub struct Tree<G: GameState> {
+    arena: Vec<Node<G>>,
+    // position hash -> node index, only kept when searching a DAG
+    transpositions: Option<HashMap<u64, usize>>,
+    // expand only one move out of each group of symmetric moves
+    symmetry_reduction: bool,
+    // propagate proven wins/losses/draws and skip solved subtrees
+    solver: bool,
 }
 
-impl Tree {
-    pub fn new(state: &Board) -> Tree {
-        let mut tree_root = Tree::default();
+impl<G: GameState> Tree<G> {
+    pub fn new(state: &G) -> Tree<G> {
+        let mut tree_root = Tree { arena: Vec::new(), transpositions: None, symmetry_reduction: false, solver: false };
+
+        let parent_index = None;
+        let origin_move = None;
+        tree_root.add_child(parent_index, origin_move, 1.0, state);
+
+        tree_root
+    }
+
+    // Builds a search DAG instead of a tree: positions reached through different
+    // move orders share a single node (and its statistics)
+    pub fn with_transpositions(state: &G) -> Tree<G> {
+        let mut tree_root = Tree { arena: Vec::new(), transpositions: Some(HashMap::new()), symmetry_reduction: false, solver: false };
 
         let parent_index = None;
         let origin_move = None;
-        tree_root.add_child(parent_index, origin_move, state);
+        tree_root.add_child(parent_index, origin_move, 1.0, state);
 
         tree_root
     }
 
+    pub fn is_dag(&self) -> bool {
+        self.transpositions.is_some()
+    }
+
+    // Only expand moves returned by GameState::get_unique_moves, so symmetric
+    // moves share one child. Must be enabled before the root is expanded.
+    pub fn set_symmetry_reduction(&mut self, state: &G) {
+        let root_id = self.get_root_index();
+        assert!(self.arena[root_id].children.is_empty(), "Root node is already expanded");
+
+        self.symmetry_reduction = true;
+        self.arena[root_id].untried_moves = state.get_unique_moves();
+    }
+
+    // Turns the tree into an MCTS-Solver: proofs of terminal nodes are propagated
+    // up the tree (see update_proof) and proven children are no longer selected
+    pub fn set_solver(&mut self) {
+        self.solver = true;
+    }
+
+    pub fn is_solver(&self) -> bool {
+        self.solver
+    }
+
+    // Tries to prove the value of `node_id` from the proofs of its children.
+    // The children are positions after the opponent's move, so a child proven
+    // as a win for the opponent makes this node a loss, and only when every
+    // move is proven a loss for the opponent is this node a win.
+    pub fn update_proof(&mut self, node_id: usize) {
+        let node = &self.arena[node_id];
+        if node.proof.is_some() || node.children.is_empty() {
+            return;
+        }
+
+        let mut all_proven = node.untried_moves.is_empty();
+        let mut opponent_win_depth: Option<u32> = None;
+        let mut has_draw = false;
+        let mut max_depth = 0;
+        for child_id in node.children.iter() {
+            let child = &self.arena[*child_id];
+            match child.proof {
+                Some(Proof::Win) => {
+                    opponent_win_depth = Some(opponent_win_depth.map_or(child.proof_depth, |d| d.min(child.proof_depth)));
+                }
+                Some(Proof::Draw) => has_draw = true,
+                Some(Proof::Loss) => (),
+                None => all_proven = false,
+            }
+            max_depth = max_depth.max(child.proof_depth);
+        }
+
+        let (proof, depth) = if let Some(depth) = opponent_win_depth {
+            (Proof::Loss, depth + 1)
+        } else if !all_proven {
+            return;
+        } else if has_draw {
+            (Proof::Draw, max_depth + 1)
+        } else {
+            (Proof::Win, max_depth + 1)
+        };
+
+        let node = &mut self.arena[node_id];
+        node.proof = Some(proof);
+        node.proof_depth = depth;
+    }
+
+    // Number of nodes allocated in the arena
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
     pub fn get_root_index(&self) -> usize {
         // Root node index is always the first element in the arena -> 0
         0
     }
 
-    pub fn get(&self, node_id: usize) -> &Node {
+    pub fn get(&self, node_id: usize) -> &Node<G> {
         &self.arena[node_id]
     }
 
-    pub fn get_mut(&mut self, node_id: usize) -> &mut Node {
+    pub fn get_mut(&mut self, node_id: usize) -> &mut Node<G> {
         &mut self.arena[node_id]
     }
 
-    pub fn add_child(&mut self, parent: Option<usize>, move_: Option<usize>, state: &Board) -> usize {
-        let new_node_index = self.arena.len();
-        let new_node = Node::new(new_node_index, parent, move_, state);
+    // Child of `parent` reached by playing `move_`, if it has been expanded
+    pub fn find_child(&self, parent: usize, move_: G::Move) -> Option<usize> {
+        let parent_node = &self.arena[parent];
+        parent_node.edges.iter().position(|edge| edge.move_ == move_).map(|index| parent_node.children[index])
+    }
+
+    // Makes `new_root` the root of the tree and drops every node that can't
+    // be reached from it. The remaining nodes are compacted to the front of
+    // the arena (the new root at index 0) with their statistics intact.
+    pub fn advance_root(&mut self, new_root: usize) {
+        // Breadth first walk assigning new indices to all reachable nodes
+        let mut new_indices: HashMap<usize, usize> = HashMap::new();
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+        new_indices.insert(new_root, 0);
+        queue.push_back(new_root);
+        while let Some(node_id) = queue.pop_front() {
+            order.push(node_id);
+            for child_id in self.arena[node_id].children.iter() {
+                if !new_indices.contains_key(child_id) {
+                    new_indices.insert(*child_id, new_indices.len());
+                    queue.push_back(*child_id);
+                }
+            }
+        }
+
+        let mut old_arena = self.arena.drain(..).map(Some).collect::<Vec<Option<Node<G>>>>();
+        for old_index in order.iter() {
+            let mut node = old_arena[*old_index].take().expect("Node visited twice!");
+            node.index = new_indices[old_index];
+            node.parents = node.parents.iter().filter_map(|p| new_indices.get(p).cloned()).collect();
+            node.children = node.children.iter().map(|c| new_indices[c]).collect();
+            self.arena.push(node);
+        }
 
-        self.arena.push(new_node);
+        if let Some(transpositions) = &mut self.transpositions {
+            transpositions.retain(|_, node_id| new_indices.contains_key(node_id));
+            for node_id in transpositions.values_mut() {
+                *node_id = new_indices[node_id];
+            }
+        }
+    }
+
+    // Adds the node for `state`, reached by playing `move_` (with the given
+    // prior) from `parent`, and returns its index. In DAG mode an already known
+    // position is linked to `parent` instead of allocating a new node.
+    pub fn add_child(&mut self, parent: Option<usize>, move_: Option<G::Move>, prior: f32, state: &G) -> usize {
+        let existing_node = match &self.transpositions {
+            Some(transpositions) => transpositions.get(&state.position_hash()).cloned(),
+            None => None,
+        };
+
+        let node_index = match existing_node {
+            Some(node_index) => {
+                if let Some(parent_id) = parent {
+                    self.arena[node_index].parents.push(parent_id);
+                }
+                node_index
+            }
+            None => {
+                let new_node_index = self.arena.len();
+                let mut new_node = Node::new(new_node_index, parent, move_, state);
+                if self.symmetry_reduction {
+                    new_node.untried_moves = state.get_unique_moves();
+                }
+                self.arena.push(new_node);
+                if let Some(transpositions) = &mut self.transpositions {
+                    transpositions.insert(state.position_hash(), new_node_index);
+                }
+                new_node_index
+            }
+        };
 
         // register child to parent node (if parent index was provided)
         if let Some(parent_id) = parent {
             let parent_node = &mut self.arena[parent_id];
-            parent_node.children.push(new_node_index);
+            parent_node.children.push(node_index);
 
             // If a selected move is provided -> remove it from the parent node's untried moves
             if let Some(selected_move) = move_ {
+                parent_node.edges.push(Edge::new(selected_move, prior));
                 // Below does the same as : self.untried_moves.remove_item(&move_).unwrap();
                 let index = parent_node.untried_moves.iter().position(|x| *x == selected_move).unwrap();
                 parent_node.untried_moves.remove(index);
             }
         }
 
-        new_node_index
+        node_index
     }
 
-    
-    pub fn select_child(&self, parent: Option<usize>) -> usize {
-        let parent_node: &Node;
+    // Returns the index into children/edges of the child of `parent` (the root
+    // if None) with the highest value according to the tree policy, and that value
+    pub fn select_child(&self, parent: Option<usize>, policy: &dyn TreePolicy<G>) -> (usize, f32) {
+        let parent_node: &Node<G>;
 
         if let Some(parent_index) = parent {
             parent_node = &self.arena[parent_index];
@@ -114,22 +388,129 @@ impl Tree {
             parent_node = &self.arena[0];
         }
 
-        if parent_node.children.len() == 0 {
+        if parent_node.children.is_empty() {
             panic!("No children to select from");
         }
 
-        let mut best_child_id = parent_node.children[0];
-        let mut best_child_ucb = parent_node.ucb1(&self.arena[best_child_id]);
+        // A solver doesn't need to sample solved subtrees any more, unless
+        // everything below this node is solved already
+        let skip_proven = self.solver &&
+            parent_node.children.iter().any(|child| self.arena[*child].proof.is_none());
+
+        let mut best_child: Option<(usize, f32)> = None;
+        for (index, (child, edge)) in parent_node.children.iter().zip(parent_node.edges.iter()).enumerate() {
+            if skip_proven && self.arena[*child].proof.is_some() {
+                continue;
+            }
 
-        for child in parent_node.children.iter() {
-            let child_ucb = parent_node.ucb1(&self.arena[*child]);
-            if child_ucb > best_child_ucb {
-                best_child_ucb = child_ucb;
-                best_child_id = *child;
+            let child_ucb = policy.child_value(parent_node, edge, &self.arena[*child]);
+            match best_child {
+                Some((_, best_child_ucb)) if child_ucb > best_child_ucb => best_child = Some((index, child_ucb)),
+                Some(_) => (),
+                None => best_child = Some((index, child_ucb)),
             }
         }
+        best_child.expect("No children to select from")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::uct::{new_tree, rng_from_seed, search_tree, uct_with_config, SearchConfig, SearchLimits};
+
+    fn searched_tree(board: &Board, transpositions: bool) -> Tree<Board> {
+        let config = SearchConfig { limits: SearchLimits::iterations(3000), transpositions, seed: Some(7), ..SearchConfig::default() };
+        let mut tree = new_tree(board, &config);
+        search_tree(&mut tree, board, &config, &mut rng_from_seed(config.seed)).expect("Game is over");
+        tree
+    }
+
+    // Walks `old` from `old_id` and `new` from `new_id` side by side, checking
+    // that both hold the same statistics, and records the position hash of
+    // every node of `new` that is visited
+    fn assert_same_subtree(old: &Tree<Board>, old_id: usize, new: &Tree<Board>, new_id: usize,
+                           state: &mut Board, hashes: &mut HashMap<usize, u64>) {
+        if let Some(hash) = hashes.insert(new_id, state.position_hash()) {
+            assert_eq!(hash, state.position_hash());
+            return;
+        }
+
+        let (old_node, new_node) = (old.get(old_id), new.get(new_id));
+        assert_eq!(new_node.index, new_id);
+        assert_eq!(new_node.stats, old_node.stats);
+        assert_eq!(new_node.edges, old_node.edges);
+        assert_eq!(new_node.untried_moves, old_node.untried_moves);
+        assert_eq!(new_node.proof, old_node.proof);
+        assert_eq!(new_node.parents.len(), new_node.parents.iter().filter(|p| new.get(**p).children.contains(&new_id)).count());
+
+        for (index, edge) in new_node.edges.iter().enumerate() {
+            state.make_move(edge.move_);
+            assert_same_subtree(old, old_node.children[index], new, new_node.children[index], state, hashes);
+            state.take_move();
+        }
+    }
+
+    #[test]
+    fn advance_root_keeps_the_subtree_and_drops_the_rest() {
+        let board = Board::with_dimensions(4, 4, 3);
+        for transpositions in [false, true] {
+            let old = searched_tree(&board, transpositions);
+            let mut new = searched_tree(&board, transpositions);
+
+            let root = old.get(old.get_root_index());
+            let (edge_index, edge) = root.edges.iter().enumerate().max_by_key(|(_, edge)| edge.stats.visits() as u32).unwrap();
+            let old_child = root.children[edge_index];
+            new.advance_root(old_child);
+            assert_eq!(new.get_root_index(), 0);
+            assert!(new.len() < old.len());
+
+            let mut state = board.clone();
+            state.make_move(edge.move_);
+            let mut hashes = HashMap::new();
+            assert_same_subtree(&old, old_child, &new, 0, &mut state, &mut hashes);
+            // every node left in the arena is reachable from the new root
+            assert_eq!(hashes.len(), new.len());
+            assert!(new.get(0).parents.is_empty());
+
+            match &new.transpositions {
+                Some(table) => {
+                    assert_eq!(table.len(), new.len());
+                    for (node_id, hash) in hashes.iter() {
+                        assert_eq!(table[hash], *node_id);
+                    }
+                }
+                None => assert!(!transpositions),
+            }
+        }
+    }
+
+    fn solved(position: &str, iterations: u32) -> Option<(Proof, u32)> {
+        let board = position.parse::<Board>().expect("Valid position");
+        let config = SearchConfig { limits: SearchLimits::iterations(iterations), solver: true, seed: Some(1), ..SearchConfig::default() };
+        uct_with_config(&board, &config).expect("Game is over").forced_result()
+    }
+
+    #[test]
+    fn solver_proves_wins_losses_and_draws() {
+        assert_eq!(solved("XX-/OO-/--- x", 1000), Some((Proof::Win, 1)));
+        // O threatens b1 and a2, X can only block one of them
+        assert_eq!(solved("O-O/-X-/O-X x", 1000), Some((Proof::Loss, 2)));
+        assert_eq!(solved("XOX/XOO/OX- x", 1000), Some((Proof::Draw, 1)));
+        assert_eq!(solved("X--/-O-/--- x", 20000), Some((Proof::Draw, 7)));
+    }
+
+    #[test]
+    fn proofs_stop_the_search() {
+        let board = "XX-/OO-/--- x".parse::<Board>().unwrap();
+        let config = SearchConfig { limits: SearchLimits::iterations(1000), solver: true, seed: Some(1), ..SearchConfig::default() };
+        let mut tree = new_tree(&board, &config);
+        let result = search_tree(&mut tree, &board, &config, &mut rng_from_seed(config.seed)).expect("Game is over");
 
-        // println!("Best child selected: {}: {}", best_child_id, best_child_ucb);
-        best_child_id
+        let root = tree.get(tree.get_root_index());
+        // proofs are for the player who just moved, O loses at the root
+        assert_eq!((root.proof, root.proof_depth), (Some(Proof::Loss), 1));
+        assert!(result.iterations < 1000);
     }
 }
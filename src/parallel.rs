@@ -0,0 +1,261 @@
+use std::iter;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::RwLock;
+use std::thread;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+
+use crate::final_move::select_final_move;
+use crate::game_state::GameState;
+use crate::search_result::{MoveStats, SearchResult};
+use crate::node_1::{Stats, Tree};
+use crate::uct::{backpropagate, build_result, expand, is_solved, new_tree, rng_from_seed, rollout, select, update_proofs,
+                 uct_with_rng, SearchConfig};
+
+// How the work of a multithreaded search is split between the threads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParallelStrategy {
+    // Every thread searches its own tree, the root move statistics of all
+    // trees are summed up at the end. The limits apply to each thread.
+    Root,
+    // All threads grow a single shared tree. Nodes and edges on the path of an
+    // unfinished iteration get a virtual loss so other threads explore elsewhere.
+    // The limits apply to the shared tree.
+    Tree,
+}
+
+// RNG of one search thread. With a seed every thread gets its own
+// deterministic stream, derived from the seed and the thread index.
+fn thread_rng(seed: Option<u64>, thread_index: usize) -> StdRng {
+    rng_from_seed(seed.map(|seed| seed.wrapping_add(thread_index as u64)))
+}
+
+// Multithreaded uct_with_config using `threads` search threads. Returns None
+// if the game is already over.
+pub fn parallel_uct<G>(rootstate: &G, config: &SearchConfig<G>, threads: usize,
+                       strategy: ParallelStrategy) -> Option<SearchResult<G::Move>>
+where
+    G: GameState + Send + Sync,
+    G::Move: Send + Sync,
+    G::Player: Send + Sync,
+{
+    assert!(threads > 0, "Search needs at least one thread");
+    if rootstate.get_moves().is_empty() {
+        return None;
+    }
+
+    match strategy {
+        ParallelStrategy::Root => root_parallel_uct(rootstate, config, threads),
+        ParallelStrategy::Tree => tree_parallel_uct(rootstate, config, threads),
+    }
+}
+
+fn root_parallel_uct<G>(rootstate: &G, config: &SearchConfig<G>, threads: usize) -> Option<SearchResult<G::Move>>
+where
+    G: GameState + Send + Sync,
+    G::Move: Send,
+    G::Player: Send,
+{
+    let start_time = Instant::now();
+
+    let results = thread::scope(|scope| {
+        let handles = (0..threads).
+            map(|thread_index| scope.spawn(move || {
+                let mut rng = thread_rng(config.seed, thread_index);
+                uct_with_rng(rootstate, config, &mut rng)
+            })).
+            collect::<Vec<_>>();
+
+        handles.into_iter().
+            map(|handle| handle.join().expect("Search thread panicked!")).
+            collect::<Option<Vec<SearchResult<G::Move>>>>()
+    })?;
+
+    // Sum up the statistics of every root move over all trees
+    let mut moves: Vec<MoveStats<G::Move>> = Vec::new();
+    for result in results.iter() {
+        for stats in result.moves.iter() {
+            match moves.iter_mut().find(|m| m.move_ == stats.move_) {
+                Some(merged) => {
+                    merged.visits += stats.visits;
+                    merged.wins += stats.wins;
+                    merged.mean = merged.wins / merged.visits;
+                    if merged.proof.is_none() {
+                        merged.proof = stats.proof;
+                        merged.proof_depth = stats.proof_depth;
+                    }
+                }
+                None => moves.push(stats.clone()),
+            }
+        }
+    }
+
+    let best_move = select_final_move(&moves, config.final_move);
+
+    // Take the principal variation from the thread that searched the best move the most
+    let principal_variation = results.
+        iter().
+        filter(|r| r.moves.iter().any(|m| m.move_ == best_move)).
+        max_by(|a, b| {
+            let visits = |r: &SearchResult<G::Move>| r.moves.iter().find(|m| m.move_ == best_move).unwrap().visits;
+            visits(a).partial_cmp(&visits(b)).unwrap()
+        }).
+        map(|r| {
+            let mut pv = r.principal_variation.clone();
+            if pv.first() != Some(&best_move) {
+                pv = vec![best_move];
+            }
+            pv
+        }).
+        unwrap_or_else(|| vec![best_move]);
+
+    Some(SearchResult {
+        best_move,
+        moves,
+        principal_variation,
+        iterations: results.iter().map(|r| r.iterations).sum(),
+        nodes: results.iter().map(|r| r.nodes).sum(),
+        elapsed: start_time.elapsed(),
+    })
+}
+
+fn tree_parallel_uct<G>(rootstate: &G, config: &SearchConfig<G>, threads: usize) -> Option<SearchResult<G::Move>>
+where
+    G: GameState + Send + Sync,
+    G::Move: Send + Sync,
+    G::Player: Send + Sync,
+{
+    assert!(!config.limits.is_unlimited(), "Search needs at least one limit");
+
+    let start_time = Instant::now();
+    // Selection and backpropagation only need a read lock, as the statistics
+    // are atomic. The write lock is taken to add nodes and to update proofs.
+    let shared_tree = RwLock::new(new_tree(rootstate, config));
+    let iterations = AtomicU32::new(0);
+
+    thread::scope(|scope| {
+        for thread_index in 0..threads {
+            let shared_tree = &shared_tree;
+            let iterations = &iterations;
+
+            scope.spawn(move || {
+                let mut rng = thread_rng(config.seed, thread_index);
+                let mut state = rootstate.clone();
+                let mut path = Vec::new();
+
+                loop {
+                    let mut moves_to_root;
+                    {
+                        let tree = shared_tree.read().unwrap();
+                        let done = iterations.load(Ordering::SeqCst);
+                        if done > 0 && (config.limits.reached(done, start_time, tree.len()) || is_solved(&tree)) {
+                            break;
+                        }
+                        iterations.fetch_add(1, Ordering::SeqCst);
+
+                        let expansion;
+                        (moves_to_root, expansion) = select(&tree, &mut state, &mut path, config, &mut rng);
+                        if let Some((move_, prior)) = expansion {
+                            drop(tree);
+                            let mut tree = shared_tree.write().unwrap();
+                            expand(&mut tree, &mut state, &mut path, move_, prior);
+                            moves_to_root += 1;
+                            path_stats(&tree, &path).for_each(Stats::add_virtual_loss);
+                        } else {
+                            path_stats(&tree, &path).for_each(Stats::add_virtual_loss);
+                        }
+                    }
+
+                    // The rollout doesn't touch the tree, so it runs without holding a lock
+                    let rollout_moves = rollout(&mut state, config, &mut rng);
+
+                    {
+                        let tree = shared_tree.read().unwrap();
+                        path_stats(&tree, &path).for_each(Stats::revert_virtual_loss);
+                        backpropagate(&tree, &state, &path);
+                    }
+                    if config.solver {
+                        update_proofs(&mut shared_tree.write().unwrap(), &path);
+                    }
+
+                    // Undo moves made during this iteration
+                    for _i in 0..moves_to_root + rollout_moves {
+                        state.take_move();
+                    }
+                }
+            });
+        }
+    });
+
+    let tree = shared_tree.into_inner().unwrap();
+    build_result(&tree, config, iterations.into_inner(), start_time.elapsed())
+}
+
+// Statistics of every node and edge on `path` (see uct::select)
+fn path_stats<'a, G: GameState>(tree: &'a Tree<G>, path: &'a [(usize, Option<usize>)]) -> impl Iterator<Item = &'a Stats> {
+    path.iter().enumerate().flat_map(move |(i, (node_id, edge_index))| {
+        let edge_stats = edge_index.map(|edge_index| &tree.get(path[i - 1].0).edges[edge_index].stats);
+        iter::once(&tree.get(*node_id).stats).chain(edge_stats)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::defines::WIN;
+    use crate::solver::Solver;
+    use crate::uct::{uct_with_config, SearchLimits};
+
+    fn assert_single_thread_matches_uct(strategy: ParallelStrategy) {
+        let board = Board::with_dimensions(4, 4, 3);
+        for transpositions in [false, true] {
+            let config = SearchConfig {
+                limits: SearchLimits::iterations(2000),
+                transpositions,
+                seed: Some(7),
+                ..SearchConfig::default()
+            };
+
+            let parallel = parallel_uct(&board, &config, 1, strategy).expect("Game is over");
+            let single = uct_with_config(&board, &config).expect("Game is over");
+            assert_eq!(parallel.best_move, single.best_move);
+            assert_eq!(parallel.moves, single.moves);
+            assert_eq!(parallel.principal_variation, single.principal_variation);
+            assert_eq!(parallel.iterations, single.iterations);
+        }
+    }
+
+    #[test]
+    fn single_root_thread_matches_uct() {
+        assert_single_thread_matches_uct(ParallelStrategy::Root);
+    }
+
+    #[test]
+    fn single_tree_thread_matches_uct() {
+        assert_single_thread_matches_uct(ParallelStrategy::Tree);
+    }
+
+    #[test]
+    fn both_strategies_find_forced_wins() {
+        let config = SearchConfig {
+            limits: SearchLimits::iterations(5000),
+            solver: true,
+            seed: Some(7),
+            ..SearchConfig::default()
+        };
+
+        for position in ["XX-/OO-/--- x", "X-O/---/--- x", "XO-/---/--- x"] {
+            let board = position.parse::<Board>().expect("Valid position");
+            let solution = Solver::new().solve(&board);
+            assert_eq!(solution.value, WIN);
+
+            for strategy in [ParallelStrategy::Root, ParallelStrategy::Tree] {
+                let result = parallel_uct(&board, &config, 3, strategy).expect("Game is over");
+                assert!(solution.best_moves.contains(&result.best_move),
+                        "{:?} played {} in {}", strategy, result.best_move, position);
+            }
+        }
+    }
+}
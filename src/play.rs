@@ -0,0 +1,221 @@
+use std::io::{self, Write};
+
+use tttoe::board::Board;
+use tttoe::defines::*;
+use tttoe::game::winner;
+use tttoe::game_state::GameState;
+use tttoe::node_1::Proof;
+use tttoe::player::{MctsPlayer, Player};
+use tttoe::search_result::SearchResult;
+use tttoe::uct::uct_with_config;
+
+use crate::cli::Options;
+use crate::search_config;
+
+const HELP: &str = "\
+Enter a move as a square index (\"4\"), a column letter and a row (\"b2\")
+or a row and a column (\"2,3\" or \"2 3\"), counted from the top left.
+
+Commands:
+    undo    take back your last move (and the engine's reply)
+    hint    ask the engine for a move
+    new     start a new game
+    swap    switch sides with the engine
+    quit    leave the game
+    help    print this message";
+
+// One line of user input
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Move(usize),
+    Undo,
+    Hint,
+    New,
+    Swap,
+    Quit,
+    Help,
+}
+
+fn parse_input(line: &str, board: &Board) -> Result<Input, String> {
+    match line.trim().to_lowercase().as_str() {
+        "undo" | "u" => Ok(Input::Undo),
+        "hint" => Ok(Input::Hint),
+        "new" => Ok(Input::New),
+        "swap" => Ok(Input::Swap),
+        "quit" | "exit" | "q" => Ok(Input::Quit),
+        "help" | "h" | "?" => Ok(Input::Help),
+        "" => Err(String::from("Type a move or a command (help for the list)")),
+        square => board.parse_square(square).map(Input::Move),
+    }
+}
+
+// Reads lines until one of them can be parsed, closed stdin counts as quit
+fn read_input(board: &Board) -> Input {
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                println!();
+                return Input::Quit;
+            }
+            Ok(_) => (),
+        }
+
+        match parse_input(&line, board) {
+            Ok(input) => return input,
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+// Takes back moves up to and including the last one made by `human`.
+// Returns false if the human hasn't moved yet.
+fn undo(board: &mut Board, human: Mark) -> bool {
+    let first_human_ply = if human == board.first_player() { 0 } else { 1 };
+    if board.ply() <= first_human_ply {
+        return false;
+    }
+
+    loop {
+        let mover = board.player_just_moved;
+        board.take_move();
+        if mover == human {
+            return true;
+        }
+    }
+}
+
+// Tells the user what the engine thinks of the move it has just played
+fn explain(board: &Board, result: &SearchResult<usize>) {
+    // measured against all the visits of the root's moves rather than the
+    // iterations of the last search, which leave out the reused subtree
+    let stats = result.best_move_stats();
+    let total_visits = result.moves.iter().map(|m| m.visits).sum::<f32>();
+    println!("Expected score {:.2} ({} of {} simulations on this move)", stats.mean, stats.visits, total_visits);
+
+    match result.forced_result() {
+        Some((Proof::Win, plies)) => println!("Forced win in {} plies", plies),
+        Some((Proof::Loss, plies)) => println!("Forced loss in {} plies unless you slip", plies),
+        Some((Proof::Draw, _)) => println!("Forced draw"),
+        None => (),
+    }
+
+    let line = result.principal_variation.iter().map(|m| board.square_name(*m)).collect::<Vec<String>>();
+    println!("Expected line: {}", line.join(" "));
+
+    let runner_up = result.moves.
+        iter().
+        filter(|m| m.move_ != result.best_move).
+        max_by(|a, b| a.visits.partial_cmp(&b.visits).expect("Visits are never NaN"));
+    if let Some(runner_up) = runner_up {
+        println!("Next best: {} (expected score {:.2}, {} simulations)",
+                 board.square_name(runner_up.move_), runner_up.mean, runner_up.visits);
+    }
+}
+
+fn print_result(board: &Board) {
+    println!("{}", board);
+    match winner(board) {
+        Mark::NoPlayer => println!("Draw"),
+        winner => println!("Winner is {:?}", winner),
+    }
+    println!("Type new to play again or quit to leave");
+}
+
+// Human against the engine on the terminal
+pub fn play_user_game(options: &Options) {
+    let first_player = options.first_player();
+    let mut board = options.empty_board().with_first_player(first_player);
+    let mut human = options.human;
+    let mut engine = MctsPlayer::new(search_config(options, 0));
+    // keep searching while waiting for the player, up to the engine's budget
+    // (see Searcher::ponder), and reply at once when that is used up
+    engine.set_pondering(true);
+    engine.set_count_reused_visits(true);
+
+    println!("You play {:?}, {:?} moves first. Type help for the list of commands", human, first_player);
+    let mut game_over = false;
+    loop {
+        let player_to_move = board.update_player_jm(board.player_just_moved);
+        let finished = board.get_result(board.player_just_moved).is_some();
+        if finished && !game_over {
+            print_result(&board);
+        }
+        game_over = finished;
+
+        if !finished && player_to_move != human {
+            let move_ = engine.choose_move(&board).expect("The engine never quits");
+            println!("Engine ({:?}) plays {}", player_to_move, board.square_name(move_));
+            if let Some(result) = &engine.last_result {
+                explain(&board, result);
+            }
+            board.make_move(move_);
+            continue;
+        }
+
+        if !finished {
+            println!("{}", board);
+        }
+
+        match read_input(&board) {
+            Input::Move(_) if finished => println!("The game is over"),
+            Input::Move(move_) => {
+                if board.make_move_safe(move_).is_err() {
+                    println!("Square {} is already taken", board.square_name(move_));
+                }
+            }
+            Input::Undo => {
+                if undo(&mut board, human) {
+                    println!("Move taken back");
+                } else {
+                    println!("Nothing to undo");
+                }
+            }
+            Input::Hint if finished => println!("The game is over"),
+            Input::Hint => {
+                if let Some(result) = uct_with_config(&board, &search_config(options, 0)) {
+                    println!("Hint: {} (expected score {:.2})", board.square_name(result.best_move), result.best_move_stats().mean);
+                }
+            }
+            Input::New => {
+                board = options.empty_board().with_first_player(first_player);
+                println!("New game, you play {:?}", human);
+            }
+            Input::Swap => {
+                human = board.update_player_jm(human);
+                println!("You play {:?} now", human);
+            }
+            Input::Quit => break,
+            Input::Help => println!("{}", HELP),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_is_a_command_or_a_square() {
+        let board = Board::new();
+        for (line, input) in [("undo", Input::Undo), ("u", Input::Undo), ("hint", Input::Hint), ("new", Input::New),
+                              ("swap", Input::Swap), ("quit", Input::Quit), ("exit", Input::Quit), ("Q", Input::Quit),
+                              ("help", Input::Help), ("?", Input::Help), ("  Hint\n", Input::Hint)] {
+            assert_eq!(parse_input(line, &board), Ok(input), "{}", line);
+        }
+
+        assert_eq!(parse_input("b2\n", &board), Ok(Input::Move(4)));
+        assert_eq!(parse_input("2,3", &board), Ok(Input::Move(5)));
+        assert_eq!(parse_input("2 3", &board), Ok(Input::Move(5)));
+        assert_eq!(parse_input("4", &board), Ok(Input::Move(4)));
+        // taken squares are still parsed, the game loop rejects them
+        assert_eq!(parse_input("a1", &"X--/---/--- o".parse::<Board>().unwrap()), Ok(Input::Move(0)));
+
+        for line in ["", "\n", "d4", "10", "undo now", "moves"] {
+            assert!(parse_input(line, &board).is_err(), "{:?}", line);
+        }
+    }
+}
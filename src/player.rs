@@ -0,0 +1,282 @@
+use std::io;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::board::Board;
+use crate::database::PositionDatabase;
+use crate::game_state::GameState;
+use crate::search_result::SearchResult;
+use crate::searcher::{PonderHandle, Searcher};
+use crate::solver::Solver;
+use crate::uct::{rng_from_seed, SearchConfig};
+
+// Anything that can pick a move in a game of tic-tac-toe: a human at the
+// terminal or one of the engines
+pub trait Player {
+    fn name(&self) -> String;
+
+    // Returns a legal move for the player to move on `board`, or None if the
+    // player quits and the game should be abandoned
+    fn choose_move(&mut self, board: &Board) -> Option<usize>;
+}
+
+// Asks for moves on stdin, re-prompting until a legal move is entered (see
+// Board::parse_square for the accepted formats). Quits when stdin is closed.
+#[derive(Debug, Default)]
+pub struct HumanPlayer;
+
+impl Player for HumanPlayer {
+    fn name(&self) -> String {
+        String::from("Human")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        let moves = board.get_moves();
+        println!("{}", board);
+        loop {
+            println!("Enter move (available: {:?})", moves);
+            let mut input_move = String::new();
+            if io::stdin().read_line(&mut input_move).expect("Failed to read line") == 0 {
+                println!("Input closed, quitting");
+                return None;
+            }
+
+            match board.parse_square(&input_move) {
+                Ok(move_) if moves.contains(&move_) => return Some(move_),
+                Ok(move_) => println!("Square {} is already taken", board.square_name(move_)),
+                Err(err) => println!("{}", err),
+            }
+        }
+    }
+}
+
+// Plays the move found by a uct search. The search tree is kept between
+// moves of the same game (see Searcher).
+#[derive(Debug)]
+pub struct MctsPlayer {
+    config: SearchConfig<Board>,
+    searcher: Option<Searcher<Board>>,
+    // keep searching in the background during the opponent's turn
+    ponder: bool,
+    pondering: Option<PonderHandle<Board>>,
+    // count the visits the root already has (from pondering and earlier
+    // searches) against the iteration limit, see set_count_reused_visits
+    count_reused_visits: bool,
+    // result of the search behind the last move played
+    pub last_result: Option<SearchResult<usize>>,
+}
+
+impl MctsPlayer {
+    pub fn new(config: SearchConfig<Board>) -> Self {
+        MctsPlayer { config, searcher: None, ponder: false, pondering: None, count_reused_visits: false, last_result: None }
+    }
+
+    pub fn set_pondering(&mut self, ponder: bool) {
+        self.ponder = ponder;
+    }
+
+    // With an iteration limit, only search for as many iterations as the root
+    // is short of the limit, and play from the reused statistics without any
+    // search once pondering has visited the root often enough
+    pub fn set_count_reused_visits(&mut self, count_reused_visits: bool) {
+        self.count_reused_visits = count_reused_visits;
+    }
+
+    // Searcher positioned at `board`: the existing one advanced by the moves
+    // played since the last search, or a new one if `board` is another game
+    fn searcher_for(&mut self, board: &Board) -> Searcher<Board> {
+        if let Some(pondering) = self.pondering.take() {
+            self.searcher = Some(pondering.stop());
+        }
+
+        if let Some(mut searcher) = self.searcher.take() {
+            let state = searcher.state();
+            let same_game = (state.width(), state.height(), state.win_length(), state.first_player()) ==
+                            (board.width(), board.height(), board.win_length(), board.first_player()) &&
+                            board.history().starts_with(state.history());
+            if same_game {
+                let new_moves = board.history()[state.ply()..].to_vec();
+                for move_ in new_moves {
+                    searcher.advance(move_);
+                }
+                return searcher;
+            }
+        }
+
+        Searcher::new(board, self.config.clone())
+    }
+}
+
+impl Player for MctsPlayer {
+    fn name(&self) -> String {
+        String::from("MCTS")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        let mut searcher = self.searcher_for(board);
+        let root_visits = searcher.tree().get(searcher.tree().get_root_index()).stats.visits() as u32;
+        let result = match self.config.limits.max_iterations {
+            Some(max) if self.count_reused_visits && root_visits >= max => searcher.result(),
+            Some(max) if self.count_reused_visits => searcher.search_with_limits(self.config.limits.with_iterations(max - root_visits)),
+            _ => searcher.search(),
+        };
+        let result = result.expect("No moves to choose from");
+        let move_ = result.best_move;
+
+        if self.ponder {
+            searcher.advance(move_);
+            self.pondering = Some(searcher.ponder());
+        } else {
+            self.searcher = Some(searcher);
+        }
+        self.last_result = Some(result);
+        Some(move_)
+    }
+}
+
+// Never loses: plays a random move out of the ones the solver proves optimal
+#[derive(Debug)]
+pub struct SolverPlayer {
+    solver: Solver,
+    rng: StdRng,
+}
+
+impl SolverPlayer {
+    pub fn new(seed: Option<u64>) -> Self {
+        SolverPlayer { solver: Solver::new(), rng: rng_from_seed(seed) }
+    }
+}
+
+impl Player for SolverPlayer {
+    fn name(&self) -> String {
+        String::from("Perfect solver")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        let solution = self.solver.solve(board);
+        Some(*solution.best_moves.choose(&mut self.rng).expect("No moves to choose from"))
+    }
+}
+
+// Perfect play from a lookup table: a random move out of the best moves
+// stored in a PositionDatabase. Positions the database doesn't hold (e.g.
+// another board size) are solved on the spot.
+#[derive(Debug)]
+pub struct DatabasePlayer {
+    database: PositionDatabase,
+    solver: Solver,
+    rng: StdRng,
+}
+
+impl DatabasePlayer {
+    pub fn new(database: PositionDatabase, seed: Option<u64>) -> Self {
+        DatabasePlayer { database, solver: Solver::new(), rng: rng_from_seed(seed) }
+    }
+}
+
+impl Player for DatabasePlayer {
+    fn name(&self) -> String {
+        String::from("Database")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        let best_moves = match self.database.lookup(board) {
+            Some(entry) => entry.best_moves,
+            None => self.solver.solve(board).best_moves,
+        };
+        Some(*best_moves.choose(&mut self.rng).expect("No moves to choose from"))
+    }
+}
+
+// Plays uniformly random moves
+#[derive(Debug)]
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: Option<u64>) -> Self {
+        RandomPlayer { rng: rng_from_seed(seed) }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        String::from("Random")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        Some(*board.get_moves().choose(&mut self.rng).expect("No moves to choose from"))
+    }
+}
+
+// Simple rule based player: win if possible, otherwise block the opponent's
+// win, otherwise take the center, otherwise a corner, otherwise anything
+#[derive(Debug)]
+pub struct HeuristicPlayer {
+    rng: StdRng,
+}
+
+impl HeuristicPlayer {
+    pub fn new(seed: Option<u64>) -> Self {
+        HeuristicPlayer { rng: rng_from_seed(seed) }
+    }
+}
+
+impl Player for HeuristicPlayer {
+    fn name(&self) -> String {
+        String::from("Heuristic")
+    }
+
+    fn choose_move(&mut self, board: &Board) -> Option<usize> {
+        let moves = board.get_moves();
+        let player = board.update_player_jm(board.player_just_moved);
+        let opponent = board.player_just_moved;
+
+        let (width, height) = (board.width(), board.height());
+        // the middle square(s), more than one on boards with an even side
+        let mut centers = Vec::new();
+        for row in [(height - 1) / 2, height / 2].iter() {
+            for col in [(width - 1) / 2, width / 2].iter() {
+                centers.push(row * width + col);
+            }
+        }
+        centers.sort();
+        centers.dedup();
+        let corners = [0, width - 1, (height - 1) * width, height * width - 1];
+
+        let candidates = [
+            board.winning_squares(player),
+            board.winning_squares(opponent),
+            centers.iter().cloned().filter(|m| moves.contains(m)).collect(),
+            corners.iter().cloned().filter(|m| moves.contains(m)).collect(),
+            moves.clone(),
+        ];
+
+        let best = candidates.iter().find(|squares| !squares.is_empty()).expect("No moves to choose from");
+        Some(*best.choose(&mut self.rng).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defines::Mark;
+    use crate::game::{play_game, winner};
+
+    #[test]
+    fn database_player_never_loses() {
+        let database = PositionDatabase::build(&Board::new());
+        let mut database_player = DatabasePlayer::new(database, Some(1));
+        let mut solver_player = SolverPlayer::new(Some(2));
+        let mut random_player = RandomPlayer::new(Some(3));
+
+        for _game in 0..10 {
+            let board = play_game(&Board::new(), &mut database_player, &mut solver_player, false);
+            assert_eq!(winner(&board), Mark::NoPlayer);
+            let board = play_game(&Board::new(), &mut random_player, &mut database_player, false);
+            assert_ne!(winner(&board), Mark::X);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+
+use crate::game_state::GameState;
+
+// Picks the moves played during the rollout (default policy) of every
+// uct iteration. `state` can be used for lookahead but must be left as it was.
+pub trait RolloutPolicy<G: GameState>: fmt::Debug + Send + Sync {
+    fn choose_move(&self, state: &mut G, moves: &[G::Move], rng: &mut dyn RngCore) -> G::Move;
+}
+
+// Plays uniformly random moves
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct UniformRollout;
+
+impl<G: GameState> RolloutPolicy<G> for UniformRollout {
+    fn choose_move(&self, _state: &mut G, moves: &[G::Move], rng: &mut dyn RngCore) -> G::Move {
+        *moves.choose(rng).expect("Move missing!")
+    }
+}
+
+// Plays a winning move if there is one, otherwise avoids moves that let the
+// opponent win on the next move (i.e. blocks their threats). Falls back to a
+// random move when every move loses. See GameState::winning_moves and
+// GameState::safe_moves.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HeuristicRollout;
+
+impl<G: GameState> RolloutPolicy<G> for HeuristicRollout {
+    fn choose_move(&self, state: &mut G, moves: &[G::Move], rng: &mut dyn RngCore) -> G::Move {
+        if let Some(move_) = state.winning_moves().choose(rng) {
+            return *move_;
+        }
+
+        match state.safe_moves().choose(rng) {
+            Some(move_) => *move_,
+            None => *moves.choose(rng).expect("Move missing!"),
+        }
+    }
+}
+
+// Scores a move in a position, higher is better
+pub type MoveScorer<G> = Arc<dyn Fn(&G, <G as GameState>::Move) -> f32 + Send + Sync>;
+
+// Plays the move with the highest score, or a uniformly random move
+// with probability epsilon
+pub struct EpsilonGreedyRollout<G: GameState> {
+    pub epsilon: f64,
+    pub scorer: MoveScorer<G>,
+}
+
+impl<G: GameState> EpsilonGreedyRollout<G> {
+    pub fn new(epsilon: f64, scorer: MoveScorer<G>) -> Self {
+        EpsilonGreedyRollout { epsilon, scorer }
+    }
+}
+
+impl<G: GameState> fmt::Debug for EpsilonGreedyRollout<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EpsilonGreedyRollout").field("epsilon", &self.epsilon).finish()
+    }
+}
+
+impl<G: GameState> RolloutPolicy<G> for EpsilonGreedyRollout<G> {
+    fn choose_move(&self, state: &mut G, moves: &[G::Move], rng: &mut dyn RngCore) -> G::Move {
+        if rng.gen_bool(self.epsilon) {
+            return *moves.choose(rng).expect("Move missing!");
+        }
+
+        let mut best_move = moves[0];
+        let mut best_score = (self.scorer)(state, best_move);
+        for move_ in moves.iter().skip(1) {
+            let score = (self.scorer)(state, *move_);
+            if score > best_score {
+                best_score = score;
+                best_move = *move_;
+            }
+        }
+
+        best_move
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::node_1::Proof;
+
+// Statistics gathered for one of the root moves
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveStats<M> {
+    pub move_: M,
+    pub visits: f32,
+    pub wins: f32,
+    // wins / visits from the point of view of the player making the move
+    pub mean: f32,
+    // proven outcome for the player making the move, if the search solved it
+    pub proof: Option<Proof>,
+    // plies from the position after the move until the end of the game (for proven moves)
+    pub proof_depth: u32,
+}
+
+// Everything a search found out about the root position
+#[derive(Debug, Clone)]
+pub struct SearchResult<M> {
+    pub best_move: M,
+    // one entry per expanded root move, in expansion order
+    pub moves: Vec<MoveStats<M>>,
+    // expected line of play, starting with best_move and following the
+    // most visited child at every level
+    pub principal_variation: Vec<M>,
+    pub iterations: u32,
+    pub nodes: usize,
+    pub elapsed: Duration,
+}
+
+impl<M: PartialEq> SearchResult<M> {
+    // Statistics of the chosen move
+    pub fn best_move_stats(&self) -> &MoveStats<M> {
+        self.moves.iter().find(|m| m.move_ == self.best_move).expect("Best move has no statistics!")
+    }
+
+    // Proven outcome of the best move for the player to move and the number of
+    // plies (including the best move) until the end of the game, e.g. a forced
+    // win in 3 plies. None if the search didn't solve the position.
+    pub fn forced_result(&self) -> Option<(Proof, u32)> {
+        let stats = self.best_move_stats();
+        stats.proof.map(|proof| (proof, stats.proof_depth + 1))
+    }
+}
+
+impl<M: fmt::Debug + PartialEq> fmt::Display for SearchResult<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stats in self.moves.iter() {
+            writeln!(f, "Move {:?}, Score {}/{} -> {}", stats.move_, stats.wins, stats.visits, stats.mean)?;
+        }
+
+        writeln!(f, "Best move {:?}, PV {:?}", self.best_move, self.principal_variation)?;
+        match self.forced_result() {
+            Some((Proof::Win, plies)) => writeln!(f, "Forced win in {} plies", plies)?,
+            Some((Proof::Loss, plies)) => writeln!(f, "Forced loss in {} plies", plies)?,
+            Some((Proof::Draw, _)) => writeln!(f, "Forced draw")?,
+            None => (),
+        }
+        write!(f, "{} iterations, {} nodes in {:?}", self.iterations, self.nodes, self.elapsed)
+    }
+}
+
+fn proof_to_json(proof: Option<Proof>) -> &'static str {
+    match proof {
+        Some(Proof::Win) => "\"win\"",
+        Some(Proof::Loss) => "\"loss\"",
+        Some(Proof::Draw) => "\"draw\"",
+        None => "null",
+    }
+}
+
+impl<M: fmt::Debug + PartialEq> SearchResult<M> {
+    // Single line JSON object, moves are written with their Debug format
+    pub fn to_json(&self) -> String {
+        let moves = self.moves.
+            iter().
+            map(|stats| format!("{{\"move\": {:?}, \"visits\": {}, \"wins\": {}, \"mean\": {}, \"proof\": {}}}",
+                                stats.move_, stats.visits, stats.wins, stats.mean, proof_to_json(stats.proof))).
+            collect::<Vec<String>>();
+        let pv = self.principal_variation.iter().map(|m| format!("{:?}", m)).collect::<Vec<String>>();
+
+        format!("{{\"best_move\": {:?}, \"principal_variation\": [{}], \"moves\": [{}], \"iterations\": {}, \"nodes\": {}, \"elapsed_ms\": {}}}",
+                self.best_move, pv.join(", "), moves.join(", "), self.iterations, self.nodes, self.elapsed.as_millis())
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+
+use crate::game_state::GameState;
+use crate::node_1::Tree;
+use crate::search_result::SearchResult;
+use crate::uct::{build_result, is_solved, new_tree, rng_from_seed, search_tree, SearchConfig, SearchLimits};
+
+// Iterations the pondering thread runs between checks of the stop flag
+const PONDER_BATCH: u32 = 256;
+
+// Node limit for pondering when the config doesn't set one
+pub const DEFAULT_PONDER_NODES: usize = 200000;
+
+// Keeps the search tree between moves. After a move is played the subtree
+// below it becomes the new root, so the statistics gathered for it while
+// searching the previous position are reused instead of thrown away.
+#[derive(Debug)]
+pub struct Searcher<G: GameState> {
+    config: SearchConfig<G>,
+    state: G,
+    tree: Tree<G>,
+    rng: StdRng,
+}
+
+impl<G: GameState> Searcher<G> {
+    pub fn new(rootstate: &G, config: SearchConfig<G>) -> Self {
+        let rng = rng_from_seed(config.seed);
+
+        Searcher {
+            tree: new_tree(rootstate, &config),
+            state: rootstate.clone(),
+            config,
+            rng,
+        }
+    }
+
+    // Position at the root of the tree
+    pub fn state(&self) -> &G {
+        &self.state
+    }
+
+    pub fn tree(&self) -> &Tree<G> {
+        &self.tree
+    }
+
+    pub fn config(&self) -> &SearchConfig<G> {
+        &self.config
+    }
+
+    // New settings apply to the following searches. Changing transpositions or
+    // symmetries only takes effect once the tree is rebuilt (see reset).
+    pub fn set_config(&mut self, config: SearchConfig<G>) {
+        self.config = config;
+    }
+
+    // Keeps growing the tree from the current root until the configured limits
+    // are reached. Iteration and time limits count from the start of this call,
+    // the node limit includes the nodes kept from earlier searches. Returns
+    // None if the game is over at the root.
+    pub fn search(&mut self) -> Option<SearchResult<G::Move>> {
+        search_tree(&mut self.tree, &self.state, &self.config, &mut self.rng)
+    }
+
+    // Same as search, but stops at `limits` instead of the configured limits
+    pub fn search_with_limits(&mut self, limits: SearchLimits) -> Option<SearchResult<G::Move>> {
+        let config = SearchConfig { limits, ..self.config.clone() };
+        search_tree(&mut self.tree, &self.state, &config, &mut self.rng)
+    }
+
+    // Statistics gathered so far for the root position, without searching any
+    // further (None if nothing has been searched yet). `iterations` is the
+    // number of visits of the root, i.e. the iterations of all searches that
+    // contributed to the current tree.
+    pub fn result(&self) -> Option<SearchResult<G::Move>> {
+        let root = self.tree.get(self.tree.get_root_index());
+        build_result(&self.tree, &self.config, root.stats.visits() as u32, Duration::from_secs(0))
+    }
+
+    // Moves the searcher to a background thread that keeps growing the tree for
+    // the current root (e.g. while the opponent is thinking) until stopped. The
+    // configured node limit (DEFAULT_PONDER_NODES if there is none) is respected,
+    // and so is the iteration limit, as a cap on the visits of the root, so that
+    // pondering never gives a move more than one search's budget. The time limit
+    // is ignored. The thread also finishes on its own once the root is solved or
+    // a batch of iterations adds no new nodes, i.e. the search keeps revisiting
+    // the same lines and has little left to find.
+    pub fn ponder(self) -> PonderHandle<G>
+    where
+        G: Send + 'static,
+        G::Move: Send,
+        G::Player: Send,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = thread::spawn(move || {
+            let mut searcher = self;
+            let max_nodes = searcher.config.limits.max_nodes.unwrap_or(DEFAULT_PONDER_NODES);
+            let max_visits = searcher.config.limits.max_iterations.unwrap_or(u32::MAX);
+            let mut batch_config = SearchConfig {
+                limits: SearchLimits { max_iterations: Some(PONDER_BATCH), max_time: None, max_nodes: Some(max_nodes) },
+                ..searcher.config.clone()
+            };
+
+            while !thread_stop.load(Ordering::Relaxed) && searcher.tree.len() < max_nodes {
+                let root_visits = searcher.tree.get(searcher.tree.get_root_index()).stats.visits() as u32;
+                if root_visits >= max_visits {
+                    break;
+                }
+                batch_config.limits.max_iterations = Some(PONDER_BATCH.min(max_visits - root_visits));
+
+                let nodes = searcher.tree.len();
+                let searched = search_tree(&mut searcher.tree, &searcher.state, &batch_config, &mut searcher.rng);
+                if searched.is_none() || is_solved(&searcher.tree) || searcher.tree.len() == nodes {
+                    break;
+                }
+            }
+
+            searcher
+        });
+
+        PonderHandle { stop, thread: Some(thread) }
+    }
+
+    // Plays `move_` on the root position and makes its subtree the new root.
+    // Nodes that can no longer be reached are discarded. If the move was never
+    // expanded the tree is started from scratch.
+    pub fn advance(&mut self, move_: G::Move) {
+        let root_id = self.tree.get_root_index();
+        let child = self.tree.find_child(root_id, move_);
+
+        self.state.make_move(move_);
+        match child {
+            Some(child_id) => self.tree.advance_root(child_id),
+            None => self.tree = new_tree(&self.state, &self.config),
+        }
+    }
+
+    // Throws the tree away and starts over from `rootstate`
+    pub fn reset(&mut self, rootstate: &G) {
+        self.state = rootstate.clone();
+        self.tree = new_tree(rootstate, &self.config);
+    }
+}
+
+// A Searcher pondering in a background thread, see Searcher::ponder.
+// Dropping the handle stops the thread and throws the searcher away.
+#[derive(Debug)]
+pub struct PonderHandle<G: GameState> {
+    stop: Arc<AtomicBool>,
+    // only None once stop() has taken the thread
+    thread: Option<thread::JoinHandle<Searcher<G>>>,
+}
+
+impl<G: GameState> PonderHandle<G> {
+    // Stops pondering and hands back the searcher with everything it has
+    // found so far. Its tree can then be advanced by the opponent's move.
+    pub fn stop(mut self) -> Searcher<G> {
+        self.stop.store(true, Ordering::Relaxed);
+        let thread = self.thread.take().expect("Pondering thread already stopped!");
+        thread.join().expect("Pondering thread panicked!")
+    }
+}
+
+impl<G: GameState> Drop for PonderHandle<G> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // a panic in the thread has nothing left to hand back
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::game_state::GameState;
+
+    #[test]
+    fn pondering_stays_within_the_iteration_limit() {
+        let config = SearchConfig {
+            limits: SearchLimits::iterations(50),
+            seed: Some(1),
+            ..SearchConfig::default()
+        };
+        let mut board = Board::new();
+        board.make_move(4);
+
+        let pondering = Searcher::new(&board, config).ponder();
+        while !pondering.thread.as_ref().unwrap().is_finished() {
+            thread::yield_now();
+        }
+        let searcher = pondering.stop();
+        assert_eq!(searcher.tree().get(searcher.tree().get_root_index()).stats.visits(), 50.0);
+    }
+
+    #[test]
+    fn dropping_the_handle_stops_pondering() {
+        let config = SearchConfig { limits: SearchLimits::nodes(1000000), seed: Some(1), ..SearchConfig::default() };
+        let pondering = Searcher::new(&Board::with_dimensions(7, 6, 4), config).ponder();
+        let stop = pondering.stop.clone();
+        drop(pondering);
+
+        assert!(stop.load(Ordering::Relaxed));
+        // the thread has been joined, so only this test still holds the flag
+        assert_eq!(Arc::strong_count(&stop), 1);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::defines::*;
+use crate::game_state::GameState;
+
+// Game-theoretic value of a position under perfect play
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution<M> {
+    // WIN, DRAW or LOSS from the point of view of the player to move
+    pub value: f32,
+    // every move that keeps that value, empty if the game is over
+    pub best_moves: Vec<M>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    // the real value is at least the stored one
+    Lower,
+    // the real value is at most the stored one
+    Upper,
+}
+
+// Negamax search with alpha-beta pruning and a transposition table. Scores are
+// 1 (win), 0 (draw) and -1 (loss) for the player to move. The transposition
+// table is kept between calls, so solving many positions of the same game
+// with one Solver gets cheaper over time.
+#[derive(Debug, Default)]
+pub struct Solver {
+    transpositions: HashMap<u64, (i8, Bound)>,
+    // positions visited by all searches so far
+    pub nodes: u64,
+}
+
+fn score_to_value(score: i8) -> f32 {
+    match score {
+        1 => WIN,
+        0 => DRAW,
+        _ => LOSS,
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Solver::default()
+    }
+
+    // Value of the position and all moves that achieve it
+    pub fn solve<G: GameState>(&mut self, state: &G) -> Solution<G::Move> {
+        let mut state = state.clone();
+        let moves = state.get_moves();
+
+        if moves.is_empty() {
+            let score = self.negamax(&mut state, -1, 1);
+            return Solution { value: score_to_value(score), best_moves: Vec::new() };
+        }
+
+        // Every root move is searched with a full window so that moves which are
+        // only as good as the best one aren't cut off
+        let mut best_score = -1;
+        let mut best_moves = Vec::new();
+        for move_ in moves {
+            state.make_move(move_);
+            let score = -self.negamax(&mut state, -1, 1);
+            state.take_move();
+
+            if score > best_score || best_moves.is_empty() {
+                best_score = score;
+                best_moves.clear();
+            }
+            if score == best_score {
+                best_moves.push(move_);
+            }
+        }
+
+        Solution { value: score_to_value(best_score), best_moves }
+    }
+
+    // Value of the position (WIN, DRAW or LOSS) for the player to move
+    pub fn value<G: GameState>(&mut self, state: &G) -> f32 {
+        let mut state = state.clone();
+        score_to_value(self.negamax(&mut state, -1, 1))
+    }
+
+    fn negamax<G: GameState>(&mut self, state: &mut G, mut alpha: i8, mut beta: i8) -> i8 {
+        self.nodes += 1;
+
+        // Result is from the point of view of the player who just moved,
+        // negate it to get the score of the player to move
+        if let Some(result) = state.get_result(state.player_just_moved()) {
+            return -((result * 2.0 - 1.0) as i8);
+        }
+
+        let hash = state.position_hash();
+        let original_alpha = alpha;
+        if let Some((score, bound)) = self.transpositions.get(&hash) {
+            match bound {
+                Bound::Exact => return *score,
+                Bound::Lower => alpha = alpha.max(*score),
+                Bound::Upper => beta = beta.min(*score),
+            }
+            if alpha >= beta {
+                return *score;
+            }
+        }
+
+        let mut best_score = -1;
+        for move_ in state.get_moves() {
+            state.make_move(move_);
+            let score = -self.negamax(state, -beta, -alpha);
+            state.take_move();
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.transpositions.insert(hash, (best_score, bound));
+
+        best_score
+    }
+}
+
+// Solves a single position with a fresh Solver
+pub fn solve<G: GameState>(state: &G) -> Solution<G::Move> {
+    Solver::new().solve(state)
+}
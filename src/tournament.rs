@@ -0,0 +1,258 @@
+use std::fmt;
+
+use crate::board::Board;
+use crate::defines::*;
+use crate::game::{play_game, winner};
+use crate::game_state::GameState;
+use crate::player::Player;
+
+// z value of a two sided 95% confidence interval
+const Z_95: f64 = 1.959964;
+
+// Expected score of a player `elo` points stronger than its opponent
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// Elo difference that corresponds to an expected `score` (0..1)
+fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+// Sequential probability ratio test of the hypotheses "player A is elo0
+// stronger than B" (H0) against "A is elo1 stronger" (H1). The match is
+// stopped as soon as one of them is accepted with the given error rates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // probability of accepting H1 when H0 is true
+    pub alpha: f64,
+    // probability of accepting H0 when H1 is true
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    // (lower, upper) log-likelihood ratio bounds for accepting H0 / H1
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    // Log-likelihood ratio of H1 against H0 for the results so far, using the
+    // normal approximation of the per game score distribution
+    pub fn llr(&self, result: &MatchResult) -> f64 {
+        let games = result.games() as f64;
+        let variance = result.score_variance();
+        if games == 0.0 || variance <= 0.0 {
+            return 0.0;
+        }
+
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        games * (s1 - s0) * (2.0 * result.score() - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn decision(&self, result: &MatchResult) -> Option<SprtDecision> {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(SprtDecision::AcceptH0)
+        } else if llr >= upper {
+            Some(SprtDecision::AcceptH1)
+        } else {
+            None
+        }
+    }
+}
+
+// Win/draw/loss counts of player A against player B
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    // set when the match was stopped early by the SPRT
+    pub sprt_decision: Option<SprtDecision>,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Average score of player A, a draw counting as half a win (0.5 before
+    // any game has been played)
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+
+        (self.wins as f64 + self.draws as f64 * 0.5) / self.games() as f64
+    }
+
+    // Variance of the score of a single game
+    fn score_variance(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+
+        let score = self.score();
+        let games = self.games() as f64;
+        (self.wins as f64 * (1.0 - score).powi(2) +
+         self.draws as f64 * (0.5 - score).powi(2) +
+         self.losses as f64 * score.powi(2)) / games
+    }
+
+    // Estimated Elo difference of A over B (infinite after a clean sweep)
+    pub fn elo(&self) -> f64 {
+        elo_from_score(self.score())
+    }
+
+    // (lower, upper) bounds of the 95% confidence interval of elo(), unbounded
+    // before any game has been played
+    pub fn elo_interval(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (f64::NEG_INFINITY, f64::INFINITY);
+        }
+
+        let score = self.score();
+        let margin = Z_95 * (self.score_variance() / self.games() as f64).sqrt();
+        (elo_from_score((score - margin).max(0.0)), elo_from_score((score + margin).min(1.0)))
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.games() == 0 {
+            return write!(f, "No games played");
+        }
+
+        let (lower, upper) = self.elo_interval();
+        writeln!(f, "Games {}: W {} / D {} / L {}, score {:.1}%",
+                 self.games(), self.wins, self.draws, self.losses, self.score() * 100.0)?;
+        write!(f, "Elo {:+.1} (95% CI {:+.1} .. {:+.1})", self.elo(), lower, upper)?;
+        match self.sprt_decision {
+            Some(SprtDecision::AcceptH0) => write!(f, "\nSPRT: H0 accepted"),
+            Some(SprtDecision::AcceptH1) => write!(f, "\nSPRT: H1 accepted"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    // maximum number of games, fewer are played if the SPRT stops the match
+    pub games: u32,
+    pub start: Board,
+    pub sprt: Option<Sprt>,
+    // print every game and the standings after each of them
+    pub verbose: bool,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig { games: 100, start: Board::new(), sprt: None, verbose: false }
+    }
+}
+
+// Plays a match between two players, alternating who moves first (A plays X
+// in the even games), and returns the results from A's point of view
+pub fn run_tournament(player_a: &mut dyn Player, player_b: &mut dyn Player, config: &TournamentConfig) -> MatchResult {
+    let mut result = MatchResult::default();
+
+    for game in 0..config.games {
+        let a_mark = if game % 2 == 0 { Mark::X } else { Mark::O };
+        let final_board = if a_mark == Mark::X {
+            play_game(&config.start, player_a, player_b, config.verbose)
+        } else {
+            play_game(&config.start, player_b, player_a, config.verbose)
+        };
+
+        // a player quit, the match ends with the games finished so far
+        if final_board.get_result(final_board.player_just_moved).is_none() {
+            break;
+        }
+
+        let winner = winner(&final_board);
+        if winner == Mark::NoPlayer {
+            result.draws += 1;
+        } else if winner == a_mark {
+            result.wins += 1;
+        } else {
+            result.losses += 1;
+        }
+
+        if config.verbose {
+            println!("{} vs {}, game {}: W {} / D {} / L {}",
+                     player_a.name(), player_b.name(), game + 1, result.wins, result.draws, result.losses);
+        }
+
+        if let Some(sprt) = &config.sprt {
+            result.sprt_decision = sprt.decision(&result);
+            if result.sprt_decision.is_some() {
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::RandomPlayer;
+
+    // Plays `moves` random moves, then quits
+    struct QuittingPlayer {
+        moves: u32,
+        random: RandomPlayer,
+    }
+
+    impl Player for QuittingPlayer {
+        fn name(&self) -> String {
+            String::from("Quitter")
+        }
+
+        fn choose_move(&mut self, board: &Board) -> Option<usize> {
+            if self.moves == 0 {
+                return None;
+            }
+            self.moves -= 1;
+            self.random.choose_move(board)
+        }
+    }
+
+    #[test]
+    fn empty_match_has_no_nan() {
+        let result = MatchResult::default();
+        assert_eq!(result.score(), 0.5);
+        assert_eq!(result.elo(), 0.0);
+        assert_eq!(result.elo_interval(), (f64::NEG_INFINITY, f64::INFINITY));
+        assert_eq!(Sprt::new(0.0, 50.0).decision(&result), None);
+        assert_eq!(result.to_string(), "No games played");
+    }
+
+    #[test]
+    fn quitting_ends_the_match() {
+        let mut quitter = QuittingPlayer { moves: 3, random: RandomPlayer::new(Some(1)) };
+        let mut random = RandomPlayer::new(Some(2));
+
+        let board = play_game(&Board::new(), &mut quitter, &mut random, false);
+        assert_eq!(board.ply(), 6);
+        assert_eq!(board.get_result(board.player_just_moved), None);
+
+        let config = TournamentConfig { games: 10, ..TournamentConfig::default() };
+        let result = run_tournament(&mut quitter, &mut random, &config);
+        assert_eq!(result.games(), 0);
+    }
+}
@@ -0,0 +1,123 @@
+use std::f32::consts::SQRT_2;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::game_state::GameState;
+use crate::node_1::{Edge, Node};
+
+// Decides which child Tree::select_child descends into: the child with the
+// highest child_value() is selected. `edge` links `parent` to `child`; in a
+// DAG the child's own statistics include the visits from its other parents,
+// so the policies take the value from the child and count the exploration
+// with the visits of the edge (as in UCT2/UCD).
+pub trait TreePolicy<G: GameState>: fmt::Debug + Send + Sync {
+    fn child_value(&self, parent: &Node<G>, edge: &Edge<G::Move>, child: &Node<G>) -> f32;
+
+    // Prior probability of `move_` being played in `state` (the parent position),
+    // stored in the edge to the child when it is expanded. `num_moves` is the number of
+    // moves available in `state`. Defaults to a uniform distribution.
+    fn prior(&self, _state: &G, _move_: G::Move, num_moves: usize) -> f32 {
+        1.0 / num_moves as f32
+    }
+
+    // Value of expanding the untried move with the highest prior (`prior`) out
+    // of `parent`, compared against the child_value of the expanded children.
+    // Defaults to infinity, i.e. all moves are expanded before a child is
+    // selected.
+    fn unexpanded_value(&self, _parent: &Node<G>, _prior: f32) -> f32 {
+        f32::INFINITY
+    }
+}
+
+// UCB1 with a configurable exploration constant:
+// Vi + C * sqrt( ln(N) / Ni ), where Vi is the estimated value of the node
+// Ni is the number of times the node has been visited from this parent,
+// N is the total number of times its parent has been visited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ucb1 {
+    pub exploration: f32,
+}
+
+impl Default for Ucb1 {
+    fn default() -> Self {
+        // C = sqrt(2) is the value from the original UCB1 paper
+        Ucb1 { exploration: SQRT_2 }
+    }
+}
+
+impl<G: GameState> TreePolicy<G> for Ucb1 {
+    fn child_value(&self, parent: &Node<G>, edge: &Edge<G::Move>, child: &Node<G>) -> f32 {
+        child.stats.score() + self.exploration * (parent.stats.visits().ln() / edge.stats.visits()).sqrt()
+    }
+}
+
+// UCB1-Tuned: replaces the constant exploration term of UCB1 with an upper
+// bound on the variance of the child's results, so children with consistent
+// results are explored less
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Ucb1Tuned;
+
+impl<G: GameState> TreePolicy<G> for Ucb1Tuned {
+    fn child_value(&self, parent: &Node<G>, edge: &Edge<G::Move>, child: &Node<G>) -> f32 {
+        let log_parent = parent.stats.visits().ln();
+        let edge_visits = edge.stats.visits();
+        let score = child.stats.score();
+        let variance = child.stats.sum_squares() / child.stats.visits() - score * score;
+        let variance_bound = variance + (2.0 * log_parent / edge_visits).sqrt();
+
+        score + (log_parent / edge_visits * variance_bound.min(0.25)).sqrt()
+    }
+}
+
+// Function returning the prior probability of a move in a position. The
+// probabilities of all legal moves in a position should sum up to 1.
+pub type PriorFn<G> = Arc<dyn Fn(&G, <G as GameState>::Move) -> f32 + Send + Sync>;
+
+// PUCT (as used by AlphaZero): Vi + C * Pi * sqrt(N) / (1 + Ni), where Pi is
+// the prior probability of the move leading to the child. Without a prior
+// function all moves get the same prior. Unexpanded moves compete with the
+// expanded children, valued with the parent's score in place of Vi, so moves
+// with a high prior are expanded and revisited first.
+pub struct Puct<G: GameState> {
+    pub exploration: f32,
+    pub priors: Option<PriorFn<G>>,
+}
+
+impl<G: GameState> Puct<G> {
+    pub fn new(exploration: f32) -> Self {
+        Puct { exploration, priors: None }
+    }
+
+    pub fn with_priors(exploration: f32, priors: PriorFn<G>) -> Self {
+        Puct { exploration, priors: Some(priors) }
+    }
+}
+
+impl<G: GameState> fmt::Debug for Puct<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Puct").
+            field("exploration", &self.exploration).
+            field("priors", &self.priors.is_some()).
+            finish()
+    }
+}
+
+impl<G: GameState> TreePolicy<G> for Puct<G> {
+    fn child_value(&self, parent: &Node<G>, edge: &Edge<G::Move>, child: &Node<G>) -> f32 {
+        child.stats.score() + self.exploration * edge.prior * parent.stats.visits().sqrt() / (1.0 + edge.stats.visits())
+    }
+
+    fn prior(&self, state: &G, move_: G::Move, num_moves: usize) -> f32 {
+        match &self.priors {
+            Some(priors) => priors(state, move_),
+            None => 1.0 / num_moves as f32,
+        }
+    }
+
+    fn unexpanded_value(&self, parent: &Node<G>, prior: f32) -> f32 {
+        // first play urgency: the parent's score seen from the player to move there
+        let visits = parent.stats.visits();
+        let value = if visits > 0.0 { 1.0 - parent.stats.score() } else { 0.5 };
+        value + self.exploration * prior * visits.sqrt()
+    }
+}
@@ -1,84 +1,413 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{FromEntropy, RngCore, SeedableRng};
 
-use crate::defines::*;
-use crate::board::Board;
+use crate::final_move::{max_robust_agree, select_final_move, FinalMovePolicy};
+use crate::game_state::GameState;
 // use crate::node::{Arena, Node, NodeId, NodeData};
-use crate::node_1::{Tree, Node};
+use crate::node_1::{Node, Tree};
+use crate::rollout_policy::{RolloutPolicy, UniformRollout};
+use crate::search_result::{MoveStats, SearchResult};
+use crate::tree_policy::{TreePolicy, Ucb1};
+
+// Iteration budget used when no other limit is given
+pub const DEFAULT_ITERATIONS: u32 = 10000;
+
+// When to stop searching. Any combination of limits can be set, the
+// search stops as soon as the first one of them is reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchLimits {
+    pub max_iterations: Option<u32>,
+    // wall-clock time budget
+    pub max_time: Option<Duration>,
+    // maximum number of nodes allocated in the tree arena
+    pub max_nodes: Option<usize>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits::iterations(DEFAULT_ITERATIONS)
+    }
+}
+
+impl SearchLimits {
+    pub fn iterations(max_iterations: u32) -> Self {
+        SearchLimits { max_iterations: Some(max_iterations), max_time: None, max_nodes: None }
+    }
+
+    pub fn time(max_time: Duration) -> Self {
+        SearchLimits { max_iterations: None, max_time: Some(max_time), max_nodes: None }
+    }
+
+    pub fn nodes(max_nodes: usize) -> Self {
+        SearchLimits { max_iterations: None, max_time: None, max_nodes: Some(max_nodes) }
+    }
+
+    pub fn with_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    pub fn with_time(mut self, max_time: Duration) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    pub fn with_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_iterations.is_none() && self.max_time.is_none() && self.max_nodes.is_none()
+    }
+
+    // Returns true once any of the limits has been reached
+    pub fn reached(&self, iterations: u32, start_time: Instant, nodes: usize) -> bool {
+        self.max_iterations.is_some_and(|max| iterations >= max) ||
+        self.max_nodes.is_some_and(|max| nodes >= max) ||
+        self.max_time.is_some_and(|max| start_time.elapsed() >= max)
+    }
+}
 
-pub fn uct(rootstate: &Board, itermax: i32) -> usize {
-    let mut arena_tree = Tree::new(rootstate);
-    // let rootnode_id = 0; // TODO: why doesn't this work ???: arena_tree.get_root_index();
-    let rootnode_id = arena_tree.get_root_index();
+// Options controlling how uct() searches
+#[derive(Debug, Clone)]
+pub struct SearchConfig<G: GameState> {
+    pub limits: SearchLimits,
+    // Formula used to pick which child to descend into during selection
+    pub tree_policy: Arc<dyn TreePolicy<G>>,
+    // Picks the moves played out from the expanded node to the end of the game
+    pub rollout_policy: Arc<dyn RolloutPolicy<G>>,
+    // Merge positions reached through different move orders into a single
+    // node, i.e. search a DAG instead of a tree
+    pub transpositions: bool,
+    // Treat moves leading to rotated/reflected copies of the same position as a
+    // single move (see GameState::get_unique_moves). Only the symmetric duplicates
+    // are dropped, so the chosen move is always a real move on the given board.
+    pub symmetries: bool,
+    pub final_move: FinalMovePolicy,
+    // Run as an MCTS-Solver: prove wins, losses and draws, stop sampling solved
+    // subtrees and stop searching as soon as the root position is solved
+    pub solver: bool,
+    // Seed for the search RNG. With the same seed (and an iteration or node
+    // limit) a search builds the same tree and returns the same move every time.
+    // None seeds the RNG from system entropy.
+    pub seed: Option<u64>,
+}
+
+impl<G: GameState> Default for SearchConfig<G> {
+    fn default() -> Self {
+        SearchConfig {
+            limits: SearchLimits::default(),
+            tree_policy: Arc::new(Ucb1::default()),
+            rollout_policy: Arc::new(UniformRollout),
+            transpositions: false,
+            symmetries: false,
+            final_move: FinalMovePolicy::default(),
+            solver: false,
+            seed: None,
+        }
+    }
+}
+
+// RNG for a search (or a player) seeded with `seed`, or from system entropy
+// if there is none
+pub fn rng_from_seed(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+// Searches `rootstate` for `itermax` iterations. Returns None if the game is
+// already over, as there is no move to choose.
+pub fn uct<G: GameState>(rootstate: &G, itermax: u32) -> Option<SearchResult<G::Move>> {
+    let config = SearchConfig { limits: SearchLimits::iterations(itermax), ..SearchConfig::default() };
+    uct_with_config(rootstate, &config)
+}
+
+pub fn uct_with_config<G: GameState>(rootstate: &G, config: &SearchConfig<G>) -> Option<SearchResult<G::Move>> {
+    let mut rng = rng_from_seed(config.seed);
+    uct_with_rng(rootstate, config, &mut rng)
+}
+
+// Same as uct_with_config but draws all random numbers from `rng` (config.seed
+// is ignored), for callers that want to use their own RNG
+pub fn uct_with_rng<G: GameState, R: RngCore>(rootstate: &G, config: &SearchConfig<G>, rng: &mut R) -> Option<SearchResult<G::Move>> {
+    let mut arena_tree = new_tree(rootstate, config);
+    search_tree(&mut arena_tree, rootstate, config, rng)
+}
+
+// Empty search tree (or DAG) for `rootstate` set up according to `config`
+pub(crate) fn new_tree<G: GameState>(rootstate: &G, config: &SearchConfig<G>) -> Tree<G> {
+    let mut arena_tree = if config.transpositions {
+        Tree::with_transpositions(rootstate)
+    } else {
+        Tree::new(rootstate)
+    };
+    if config.symmetries {
+        arena_tree.set_symmetry_reduction(rootstate);
+    }
+    if config.solver {
+        arena_tree.set_solver();
+    }
+
+    arena_tree
+}
+
+// Grows `arena_tree`, whose root is `rootstate`, until the limits of `config`
+// are reached. The tree may already contain statistics from earlier searches.
+// Returns None without searching if the game is over at the root.
+pub(crate) fn search_tree<G: GameState>(arena_tree: &mut Tree<G>, rootstate: &G, config: &SearchConfig<G>,
+                                        rng: &mut dyn RngCore) -> Option<SearchResult<G::Move>> {
+    assert!(!config.limits.is_unlimited(), "Search needs at least one limit");
+
+    let rootnode = arena_tree.get(arena_tree.get_root_index());
+    if rootnode.children.is_empty() && rootnode.untried_moves.is_empty() {
+        return None;
+    }
+
+    let start_time = Instant::now();
 
     let mut state = rootstate.clone();
-    for _i in 0..itermax {
-        let mut node_id = rootnode_id;
-        let mut moves_to_root = 0;
-        
-        // Select state
-        // node is fully expanded and non-terminal
-        // FIXME: get better access to Node info
-        while arena_tree.get(node_id).untried_moves.len() == 0 && arena_tree.get(node_id).children.len() > 0 {
-            node_id = arena_tree.select_child(Some(node_id));
-            state.make_move(arena_tree.get(node_id).move_.expect("Move missing!"));
-            moves_to_root += 1;
+    // Nodes visited during the current iteration, each with the index of the
+    // edge it was reached through. In a DAG a node can have several parents,
+    // so results are backpropagated along the path actually taken instead of
+    // following parent links.
+    let mut path = Vec::new();
+    let mut iterations = 0;
+    // Always run at least one iteration so the root has a child to pick
+    while iterations == 0 || !config.limits.reached(iterations, start_time, arena_tree.len()) {
+        if is_solved(arena_tree) {
+            break;
         }
+        iterations += 1;
+        run_iteration(arena_tree, &mut state, &mut path, config, rng);
+    }
+
+    if config.final_move == FinalMovePolicy::MaxRobustChild {
+        // Keep searching while the most visited and the best scoring child differ,
+        // for at most as many iterations again (and never past the node or time limit)
+        let extension_limit = iterations * 2;
+        while iterations < extension_limit &&
+              config.limits.max_nodes.is_none_or(|max| arena_tree.len() < max) &&
+              config.limits.max_time.is_none_or(|max| start_time.elapsed() < max) &&
+              !is_solved(arena_tree) &&
+              !max_robust_agree(&root_move_stats(arena_tree)) {
+            iterations += 1;
+            run_iteration(arena_tree, &mut state, &mut path, config, rng);
+        }
+    }
+
+    build_result(arena_tree, config, iterations, start_time.elapsed())
+}
+
+// Summarises the statistics currently stored in the tree, None if the root
+// has no expanded child yet.
+pub(crate) fn build_result<G: GameState>(arena_tree: &Tree<G>, config: &SearchConfig<G>, iterations: u32,
+                                         elapsed: Duration) -> Option<SearchResult<G::Move>> {
+    let moves = root_move_stats(arena_tree);
+    if moves.is_empty() {
+        return None;
+    }
+    let best_move = select_final_move(&moves, config.final_move);
+
+    let mut principal_variation = vec![best_move];
+    let mut node_id = arena_tree.find_child(arena_tree.get_root_index(), best_move).expect("Best move not expanded!");
+    while let Some(index) = most_visited_edge(arena_tree, node_id) {
+        let node = arena_tree.get(node_id);
+        principal_variation.push(node.edges[index].move_);
+        node_id = node.children[index];
+    }
+
+    Some(SearchResult {
+        best_move,
+        moves,
+        principal_variation,
+        iterations,
+        nodes: arena_tree.len(),
+        elapsed,
+    })
+}
+
+// One select -> expand -> rollout -> backpropagate pass starting at the root.
+// `state` must be the root position and is restored to it afterwards.
+fn run_iteration<G: GameState>(arena_tree: &mut Tree<G>, state: &mut G, path: &mut Vec<(usize, Option<usize>)>,
+                               config: &SearchConfig<G>, rng: &mut dyn RngCore) {
+    let (mut moves_to_root, expansion) = select(arena_tree, state, path, config, rng);
+    if let Some((move_, prior)) = expansion {
+        expand(arena_tree, state, path, move_, prior);
+        moves_to_root += 1;
+    }
+    moves_to_root += rollout(state, config, rng);
+    backpropagate(arena_tree, state, path);
+    update_proofs(arena_tree, path);
+
+    // Undo moves made during this iteration
+    for _i in 0..moves_to_root {
+        state.take_move();
+    }
+}
+
+// Walks down from the root, selecting children with the tree policy, until it
+// reaches a node with a move worth expanding or the end of the game. `path`
+// receives the visited nodes with the index of the edge leading to them from
+// the previous node (None for the root), `state` the moves played. Returns the
+// number of moves played and the move to expand (see expand) with its prior.
+pub(crate) fn select<G: GameState>(arena_tree: &Tree<G>, state: &mut G, path: &mut Vec<(usize, Option<usize>)>,
+                                   config: &SearchConfig<G>, rng: &mut dyn RngCore) -> (usize, Option<(G::Move, f32)>) {
+    let mut node_id = arena_tree.get_root_index();
+    let mut moves_to_root = 0;
+    path.clear();
+    path.push((node_id, None));
+
+    loop {
+        let node = arena_tree.get(node_id);
+        let mut selected = None;
         // Expand
-        // If we can expand (i.e. state/node is non-terminal)
-        if arena_tree.get(node_id).untried_moves.len() > 0 {
-            let move_ = arena_tree.get(node_id).untried_moves.choose(&mut rand::thread_rng());
-            let move_ = *move_.expect("Move missing!"); // unpack move from Option
-            state.make_move(move_);
-            moves_to_root += 1;
-            
-            node_id = arena_tree.add_child(Some(node_id), Some(move_), &state);
+        // the untried move with the highest prior, unless the tree policy values
+        // an expanded child higher (by default every move is expanded first)
+        if !node.untried_moves.is_empty() {
+            let (move_, prior) = best_untried_move(node, state, config, rng);
+            let unexpanded_value = config.tree_policy.unexpanded_value(node, prior);
+            if !node.children.is_empty() && unexpanded_value < f32::INFINITY {
+                selected = Some(arena_tree.select_child(Some(node_id), config.tree_policy.as_ref()));
+            }
+
+            if selected.is_none_or(|(_, child_value)| unexpanded_value >= child_value) {
+                return (moves_to_root, Some((move_, prior)));
+            }
         }
 
-        // Rollout
-        // While state is non-terminal
-        while state.get_result(state.player_just_moved).is_none() {
-            let moves = state.get_moves();
-            let m = moves.choose(&mut rand::thread_rng());
-            let m = *m.expect("Move missing!"); // unpack move from Option
-            state.make_move(m);
-            moves_to_root += 1;
+        // node is terminal
+        if node.children.is_empty() {
+            return (moves_to_root, None);
         }
 
-        // Backpropagate
-        // Backpropagate from the expanded node and work back to the root node
-        loop {
-            let game_result = state.get_result(arena_tree.get(node_id).player_just_moved).expect("No game result!");
-            // println!("Update node results: {}, res {}", node_id, game_result);
-            arena_tree.get_mut(node_id).update(game_result);
-
-            if let Some(parent_id) = arena_tree.get(node_id).parent {
-                node_id = parent_id;
-            } else {
-                break;
-            }
+        // Select
+        let (index, _) = selected.unwrap_or_else(|| arena_tree.select_child(Some(node_id), config.tree_policy.as_ref()));
+        state.make_move(node.edges[index].move_);
+        node_id = node.children[index];
+        path.push((node_id, Some(index)));
+        moves_to_root += 1;
+    }
+}
+
+// Plays `move_` on `state`, the position of the last node on `path`, adds the
+// child it leads to and appends it to `path`. The move may have been expanded
+// by another thread since it was selected, the existing child is used then.
+pub(crate) fn expand<G: GameState>(arena_tree: &mut Tree<G>, state: &mut G, path: &mut Vec<(usize, Option<usize>)>,
+                                   move_: G::Move, prior: f32) {
+    let (node_id, _) = *path.last().expect("Path is empty!");
+    state.make_move(move_);
+
+    let index = match arena_tree.get(node_id).edges.iter().position(|edge| edge.move_ == move_) {
+        Some(index) => index,
+        None => {
+            arena_tree.add_child(Some(node_id), Some(move_), prior, state);
+            arena_tree.get(node_id).children.len() - 1
         }
-        
-        // Undo moves made during this iteration
-        for _i in 0..moves_to_root {
-            state.take_move();
+    };
+    path.push((arena_tree.get(node_id).children[index], Some(index)));
+}
+
+// Untried move of `node` with the highest prior (ties are broken at random)
+// and its prior. `state` is the position of the node.
+fn best_untried_move<G: GameState>(node: &Node<G>, state: &G, config: &SearchConfig<G>,
+                                   rng: &mut dyn RngCore) -> (G::Move, f32) {
+    let num_moves = node.untried_moves.len() + node.children.len();
+    let priors = node.untried_moves.
+        iter().
+        map(|move_| config.tree_policy.prior(state, *move_, num_moves)).
+        collect::<Vec<f32>>();
+    let best_prior = priors.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let best_moves = node.untried_moves.
+        iter().
+        zip(priors.iter()).
+        filter(|(_, prior)| **prior == best_prior).
+        map(|(move_, _)| *move_).
+        collect::<Vec<G::Move>>();
+
+    (*best_moves.choose(rng).expect("Move missing!"), best_prior)
+}
+
+// Plays `state` out to the end of the game, returns the number of moves played
+pub(crate) fn rollout<G: GameState>(state: &mut G, config: &SearchConfig<G>, rng: &mut dyn RngCore) -> usize {
+    let mut moves_played = 0;
+
+    // While state is non-terminal
+    while state.get_result(state.player_just_moved()).is_none() {
+        let moves = state.get_moves();
+        let m = config.rollout_policy.choose_move(state, &moves, rng);
+        state.make_move(m);
+        moves_played += 1;
+    }
+
+    moves_played
+}
+
+// Updates every node and edge on `path` with the result of the finished game in `state`
+pub(crate) fn backpropagate<G: GameState>(arena_tree: &Tree<G>, state: &G, path: &[(usize, Option<usize>)]) {
+    // Backpropagate from the expanded node and work back to the root node
+    for (i, (node_id, edge_index)) in path.iter().enumerate().rev() {
+        let node = arena_tree.get(*node_id);
+        let game_result = state.get_result(node.player_just_moved).expect("No game result!");
+        node.stats.update(game_result);
+        if let Some(edge_index) = edge_index {
+            let (parent_id, _) = path[i - 1];
+            arena_tree.get(parent_id).edges[*edge_index].stats.update(game_result);
         }
     }
+}
 
-    let rootnode = arena_tree.get(rootnode_id);
-    let best_node = arena_tree.get(rootnode.children[0]);
-    let mut best_move = best_node.move_.expect("No move!");
-    let mut best_move_score = best_node.score;
+// Proves what can be proven about the nodes on `path` (MCTS-Solver only)
+pub(crate) fn update_proofs<G: GameState>(arena_tree: &mut Tree<G>, path: &[(usize, Option<usize>)]) {
+    if arena_tree.is_solver() {
+        for (node_id, _) in path.iter().rev() {
+            arena_tree.update_proof(*node_id);
+        }
+    }
+}
+
+// True once an MCTS-Solver has proven the value of the root position
+pub(crate) fn is_solved<G: GameState>(arena_tree: &Tree<G>) -> bool {
+    arena_tree.is_solver() && arena_tree.get(arena_tree.get_root_index()).proof.is_some()
+}
+
+fn root_move_stats<G: GameState>(tree: &Tree<G>) -> Vec<MoveStats<G::Move>> {
+    let rootnode = tree.get(tree.get_root_index());
+    rootnode.children.
+        iter().
+        zip(rootnode.edges.iter()).
+        map(|(child_id, edge)| {
+            let child = tree.get(*child_id);
+            MoveStats {
+                move_: edge.move_,
+                visits: edge.stats.visits(),
+                wins: edge.stats.wins(),
+                mean: edge.stats.score(),
+                proof: child.proof,
+                proof_depth: child.proof_depth,
+            }
+        }).
+        collect::<Vec<MoveStats<G::Move>>>()
+}
 
-    for child_id in rootnode.children.iter() {
-        let child = arena_tree.get(*child_id);
-        println!("Move {}, Score {}/{} -> {}", child.move_.expect("No move!"), child.wins, child.visits, child.score);
-        if best_move_score < child.score {
-            best_move_score = child.score;
-            best_move = child.move_.expect("No move!");
+// Index of the most visited edge out of `node_id`
+fn most_visited_edge<G: GameState>(tree: &Tree<G>, node_id: usize) -> Option<usize> {
+    let edges = &tree.get(node_id).edges;
+    let mut best_edge: Option<usize> = None;
+    for (index, edge) in edges.iter().enumerate() {
+        match best_edge {
+            Some(best_index) if edges[best_index].stats.visits() >= edge.stats.visits() => (),
+            _ => best_edge = Some(index),
         }
     }
-   
-    return best_move;
+
+    best_edge
 }
 
 // Add stuct for return result
@@ -142,3 +471,118 @@ pub fn uct(rootstate: &Board, itermax: i32) -> usize {
 //     }
 //     (0.0, 0.0)
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::final_move::SECURE_CHILD_CONSTANT;
+
+    // Searches the position with a fixed seed and returns the tree and result
+    fn seeded_search(board: &Board, config: &SearchConfig<Board>) -> (Tree<Board>, SearchResult<usize>) {
+        let mut tree = new_tree(board, config);
+        let mut rng = rng_from_seed(config.seed);
+        let result = search_tree(&mut tree, board, config, &mut rng).expect("Game is over");
+        (tree, result)
+    }
+
+    fn assert_same_tree(a: &Tree<Board>, b: &Tree<Board>) {
+        assert_eq!(a.len(), b.len());
+        for node_id in 0..a.len() {
+            let (node_a, node_b) = (a.get(node_id), b.get(node_id));
+            assert_eq!(node_a.children, node_b.children);
+            assert_eq!(node_a.edges, node_b.edges);
+            assert_eq!(node_a.untried_moves, node_b.untried_moves);
+            assert_eq!(node_a.stats, node_b.stats);
+        }
+    }
+
+    #[test]
+    fn same_seed_builds_same_tree() {
+        let board = Board::with_dimensions(4, 4, 3);
+        for transpositions in [false, true] {
+            let config = SearchConfig {
+                limits: SearchLimits::iterations(2000),
+                transpositions,
+                seed: Some(42),
+                ..SearchConfig::default()
+            };
+
+            let (tree_a, result_a) = seeded_search(&board, &config);
+            let (tree_b, result_b) = seeded_search(&board, &config);
+            assert_same_tree(&tree_a, &tree_b);
+            assert_eq!(result_a.best_move, result_b.best_move);
+            assert_eq!(result_a.moves, result_b.moves);
+            assert_eq!(result_a.principal_variation, result_b.principal_variation);
+
+            let result_c = uct_with_config(&board, &config).expect("Game is over");
+            assert_eq!(result_a.moves, result_c.moves);
+        }
+    }
+
+    // 20 seeded iterations on "X--/-O-/--- x": b3 (square 3) has the best mean
+    // after 3 lucky visits, while c3 (7) has the most visits
+    fn short_search_config(final_move: FinalMovePolicy) -> SearchConfig<Board> {
+        SearchConfig { limits: SearchLimits::iterations(20), final_move, seed: Some(3), ..SearchConfig::default() }
+    }
+
+    #[test]
+    fn final_move_policies_disagree_after_a_short_search() {
+        let board = "X--/-O-/--- x".parse::<Board>().unwrap();
+        let search = |final_move| uct_with_config(&board, &short_search_config(final_move)).expect("Game is over");
+
+        let max = search(FinalMovePolicy::MaxChild);
+        let robust = search(FinalMovePolicy::RobustChild);
+        let secure = search(FinalMovePolicy::SecureChild(SECURE_CHILD_CONSTANT));
+        // the policies only differ in the pick, the search is the same
+        assert_eq!(max.moves, robust.moves);
+        assert_eq!(max.moves, secure.moves);
+        assert!(!max_robust_agree(&max.moves));
+
+        assert_eq!(max.best_move, 3);
+        assert_eq!(robust.best_move, 7);
+        // 3 visits are too few for b3's mean to be trusted
+        assert_eq!(secure.best_move, 7);
+    }
+
+    #[test]
+    fn max_robust_extends_the_search_until_the_picks_agree() {
+        let board = "X--/-O-/--- x".parse::<Board>().unwrap();
+        let result = uct_with_config(&board, &short_search_config(FinalMovePolicy::MaxRobustChild)).expect("Game is over");
+
+        assert!(result.iterations > 20 && result.iterations <= 40);
+        assert!(max_robust_agree(&result.moves));
+        assert_eq!(result.best_move, 3);
+
+        // no extension when the picks already agree
+        let config = SearchConfig { seed: Some(0), ..short_search_config(FinalMovePolicy::MaxRobustChild) };
+        let result = uct_with_config(&Board::new(), &config).expect("Game is over");
+        assert_eq!(result.iterations, 20);
+    }
+
+    // Checks that every node of the tree below `node_id` only expands unique moves
+    fn assert_only_unique_moves(tree: &Tree<Board>, node_id: usize, state: &mut Board) {
+        let node = tree.get(node_id);
+        let mut moves = node.edges.iter().map(|edge| edge.move_).chain(node.untried_moves.iter().cloned()).collect::<Vec<usize>>();
+        moves.sort();
+        assert_eq!(moves, state.get_unique_moves());
+
+        for (edge, child_id) in node.edges.iter().zip(node.children.iter()) {
+            state.make_move(edge.move_);
+            assert_only_unique_moves(tree, *child_id, state);
+            state.take_move();
+        }
+    }
+
+    #[test]
+    fn symmetric_moves_share_one_child() {
+        let board = Board::new();
+        let config = SearchConfig { limits: SearchLimits::iterations(2000), symmetries: true, seed: Some(1), ..SearchConfig::default() };
+        let (tree, result) = seeded_search(&board, &config);
+
+        let mut root_moves = result.moves.iter().map(|m| m.move_).collect::<Vec<usize>>();
+        root_moves.sort();
+        assert_eq!(root_moves, vec![0, 1, 4]);
+        assert_only_unique_moves(&tree, tree.get_root_index(), &mut board.clone());
+    }
+}
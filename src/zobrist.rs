@@ -0,0 +1,37 @@
+use crate::defines::Mark;
+
+// Zobrist keys are derived on the fly from a fixed seed with splitmix64
+// instead of being drawn from an RNG, so hashes are identical between runs
+// (and can be stored in opening books) without carrying key tables around.
+const ZOBRIST_SEED: u64 = 0x7474_746f_655f_7a6f;
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Key for a mark placed on a given square
+pub fn square_key(square: usize, mark: Mark) -> u64 {
+    let mark_offset = match mark {
+        Mark::X => 1,
+        Mark::O => 2,
+        Mark::NoPlayer => return 0,
+    };
+
+    splitmix64(ZOBRIST_SEED ^ ((square as u64) << 2 | mark_offset))
+}
+
+// Key toggled on every move, so positions with the same marks but a
+// different player to move hash differently
+pub fn side_key() -> u64 {
+    splitmix64(ZOBRIST_SEED ^ 3)
+}
+
+// Initial hash of an empty board, so that boards of different sizes or
+// win lengths never share a hash
+pub fn dimensions_key(width: usize, height: usize, k: usize) -> u64 {
+    let dims = (width as u64) << 32 | (height as u64) << 16 | k as u64;
+    splitmix64(splitmix64(ZOBRIST_SEED) ^ dims)
+}